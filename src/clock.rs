@@ -0,0 +1,101 @@
+use std::error::Error;
+use std::fmt;
+use std::ops::Deref;
+#[cfg(test)]
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+
+use crate::util;
+
+/// Where the server gets the time from, so tests can pin it.
+pub trait Clock: Send + Sync {
+    /// The current time, in ms since the Unix epoch.
+    fn now(&self) -> Result<i64, Box<dyn Error>>;
+}
+
+/// The real time, from `util::now`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Result<i64, Box<dyn Error>> {
+        util::now()
+    }
+}
+
+/// A clock that only moves when told to. Clones share the same time, so a
+/// test can keep one and hand another to the server.
+#[cfg(test)]
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock(Arc<AtomicI64>);
+
+#[cfg(test)]
+impl ManualClock {
+    /// A clock stopped at `now` ms since the epoch.
+    pub fn new(now: i64) -> Self {
+        ManualClock(Arc::new(AtomicI64::new(now)))
+    }
+
+    pub fn set(&self, now: i64) {
+        self.0.store(now, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, ms: i64) {
+        self.0.fetch_add(ms, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+impl Clock for ManualClock {
+    fn now(&self) -> Result<i64, Box<dyn Error>> {
+        Ok(self.0.load(Ordering::SeqCst))
+    }
+}
+
+/// The clock a `Config` carries, shared by every clone of it.
+/// Defaults to the `SystemClock`.
+#[derive(Clone)]
+pub struct SharedClock(Arc<dyn Clock>);
+
+impl SharedClock {
+    pub fn new<C: Clock + 'static>(clock: C) -> Self {
+        SharedClock(Arc::new(clock))
+    }
+}
+
+impl Default for SharedClock {
+    fn default() -> Self {
+        SharedClock::new(SystemClock)
+    }
+}
+
+impl Deref for SharedClock {
+    type Target = dyn Clock;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
+
+impl fmt::Debug for SharedClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedClock")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_shares_a_manual_clock() {
+        let manual = ManualClock::new(1000);
+        let shared = SharedClock::new(manual.clone());
+        assert_eq!(shared.now().unwrap(), 1000);
+
+        manual.advance(250);
+        assert_eq!(shared.clone().now().unwrap(), 1250);
+        manual.set(0);
+        assert_eq!(shared.now().unwrap(), 0);
+    }
+}
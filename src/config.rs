@@ -1,11 +1,98 @@
 use serde_derive::{Deserialize, Serialize};
 use std::path::PathBuf;
 
+use crate::clock::SharedClock;
+use crate::database::retention::RetentionRule;
+use crate::notifier::ChangeNotifier;
+
+/// The server config, as read from `config.toml`. Anything the file leaves
+/// out keeps its `Default`.
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
 pub struct Config {
     pub ip:                 String,
     pub port:               u16,
     pub db:                 PathBuf,
     pub test_db:            PathBuf,
     pub app_name:           String,
+    /// Where uploads are spooled before they're written to the db.
+    pub file_tmp_path:      PathBuf,
+    /// Largest packet accepted on upload, in bytes.
+    pub max_packet_size:    u64,
+    /// Store packets whose bytes match one already stored, instead of
+    /// answering with the existing packet's uuid.
+    pub allow_duplicate_packets: bool,
+    /// How long an upload's `Idempotency-Key` is remembered, in seconds.
+    pub idempotency_window_secs: u64,
+    /// How long an upload can hold its `Idempotency-Key` before a retry may
+    /// take it over, in seconds. It covers a server that died mid-upload, so
+    /// it should outlast the slowest upload.
+    pub idempotency_lease_secs: u64,
+    /// How often retention rules are enforced, in seconds. 0 turns it off.
+    pub retention_interval_secs: u64,
+    /// Most packets purged per transaction while enforcing retention.
+    pub retention_batch_size: u32,
+    /// How long packets of each filetype are kept. Filetypes without a
+    /// rule are kept forever.
+    #[serde(default)]
+    pub retention:          Vec<RetentionRule>,
+    /// How long deleted packets stay in the trash, restorable, before
+    /// they're purged for good, in seconds.
+    pub trash_grace_secs:   u64,
+    /// How often expired single values are swept out of the db, in
+    /// seconds. 0 turns it off; expired values still read as missing.
+    pub value_sweep_interval_secs: u64,
+    /// What everything that timestamps rows asks for the time. Not
+    /// configurable from a file; tests swap in a `ManualClock`.
+    #[serde(skip)]
+    pub clock:              SharedClock,
+    /// Announces every write of a single value to whoever is watching.
+    #[serde(skip)]
+    pub value_changes:      ChangeNotifier,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            ip: "127.0.0.1".to_string(),
+            port: 8000,
+            app_name: "TLM Server".to_string(),
+            db: PathBuf::from("./tlm.db"),
+            test_db: PathBuf::from("./test.db"),
+            file_tmp_path: PathBuf::from("./temp"),
+            max_packet_size: 512 * 1024 * 1024,
+            allow_duplicate_packets: false,
+            idempotency_window_secs: 24 * 60 * 60,
+            idempotency_lease_secs: 10 * 60,
+            retention_interval_secs: 60 * 60,
+            retention_batch_size: 100,
+            retention: Vec::new(),
+            trash_grace_secs: 7 * 24 * 60 * 60,
+            value_sweep_interval_secs: 60,
+            clock: Default::default(),
+            value_changes: Default::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_reads_config_toml_over_the_defaults() {
+        let config: Config = toml::from_str(include_str!("../config.toml")).unwrap();
+        assert_eq!(config.retention_interval_secs, 3600);
+        assert_eq!(config.file_tmp_path, PathBuf::from("./temp"));
+        assert_eq!(config.app_name, Config::default().app_name);
+
+        let config: Config = toml::from_str(
+            "port = 9000\n[[retention]]\nfiletype = 'log'\nkeep_last = 10\n",
+        )
+        .unwrap();
+        assert_eq!(config.port, 9000);
+        assert_eq!(config.db, Config::default().db);
+        assert_eq!(config.retention[0].keep_last, Some(10));
+        assert_eq!(config.retention[0].max_age_secs, None);
+    }
 }
@@ -1,15 +1,13 @@
 pub mod connection;
 pub mod single_value;
+pub mod level_0;
+pub mod idempotency;
+pub mod metadata_schema;
+pub mod metadata_index;
+pub mod retention;
+pub mod legal_hold;
+pub mod metadata_history;
+pub mod tag;
 pub mod migrations;
 pub mod context;
-pub mod sqlite;
 
-use context::DbContext;
-
-pub fn init() {
-
-    let db = DbContext::new().init();
-
-    // db.init_level_0_packet_store();
-    // db.init_single_value_store();
-}
\ No newline at end of file
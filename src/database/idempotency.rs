@@ -0,0 +1,148 @@
+use rusqlite::{params, Result, Connection};
+
+use super::context::TLM_IDEMPOTENCY_KEY_TABLE;
+
+/// What became of an attempt to claim an idempotency key.
+#[derive(Debug, PartialEq)]
+pub enum Reservation {
+    /// The key is new; the caller should do the work and then `complete` it.
+    Reserved,
+    /// Another request holding the key hasn't finished yet.
+    InProgress,
+    /// The key has already been answered with this reply.
+    Completed(StoredReply),
+}
+
+/// A reply recorded against an idempotency key.
+#[derive(Debug, PartialEq)]
+pub struct StoredReply {
+    pub status: u16,
+    pub content_type: String,
+    pub body: String,
+}
+
+/// Claims `key` on `endpoint` for a request arriving at `now`, unless it is
+/// already held. The same key on another endpoint is a different key.
+///
+/// Keys created before `expires_before` are forgotten first, so they can be
+/// claimed afresh. So are keys still in progress that were claimed before
+/// `abandoned_before`: the request holding them is taken to have died with
+/// the server.
+pub fn reserve(
+    conn: &Connection,
+    endpoint: &str,
+    key: &str,
+    now: i64,
+    expires_before: i64,
+    abandoned_before: i64,
+) -> Result<Reservation> {
+    conn.execute(
+        &format!(
+            "delete from {} where createdate < ?1 or (status is null and createdate < ?2)",
+            TLM_IDEMPOTENCY_KEY_TABLE,
+        ),
+        params![expires_before, abandoned_before],
+    )?;
+
+    let inserted = conn.execute(
+        &format!(
+            "insert or ignore into {} (endpoint, key, createdate) values (?1, ?2, ?3)",
+            TLM_IDEMPOTENCY_KEY_TABLE,
+        ),
+        params![endpoint, key, now],
+    )?;
+    if inserted > 0 {
+        return Ok(Reservation::Reserved);
+    }
+
+    conn.query_row(
+        &format!(
+            "select status, content_type, body from {} where endpoint = ?1 and key = ?2",
+            TLM_IDEMPOTENCY_KEY_TABLE,
+        ),
+        params![endpoint, key],
+        |row| {
+            Ok(match row.get::<_, Option<u16>>(0)? {
+                None => Reservation::InProgress,
+                Some(status) => Reservation::Completed(StoredReply {
+                    status,
+                    content_type: row.get(1)?,
+                    body: row.get(2)?,
+                }),
+            })
+        },
+    )
+}
+
+/// Records the reply to the request holding `key` on `endpoint`.
+pub fn complete(conn: &Connection, endpoint: &str, key: &str, reply: &StoredReply) -> Result<()> {
+    conn.execute(
+        &format!(
+            "update {} set status = ?3, content_type = ?4, body = ?5 where endpoint = ?1 and key = ?2",
+            TLM_IDEMPOTENCY_KEY_TABLE,
+        ),
+        params![endpoint, key, reply.status, reply.content_type, reply.body],
+    )?;
+    Ok(())
+}
+
+/// Gives up `key` on `endpoint` without a reply, so a retry can claim it again.
+pub fn release(conn: &Connection, endpoint: &str, key: &str) -> Result<()> {
+    conn.execute(
+        &format!(
+            "delete from {} where endpoint = ?1 and key = ?2 and status is null",
+            TLM_IDEMPOTENCY_KEY_TABLE,
+        ),
+        params![endpoint, key],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migrations;
+
+    #[test]
+    fn it_replays_completed_keys_until_they_expire() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrations::apply_all(&conn).unwrap();
+
+        assert_eq!(reserve(&conn, "/packets", "k", 1000, 0, 0).unwrap(), Reservation::Reserved);
+        assert_eq!(reserve(&conn, "/packets", "k", 1001, 0, 0).unwrap(), Reservation::InProgress);
+
+        let reply = StoredReply { status: 200, content_type: "application/json".into(), body: "{}".into() };
+        complete(&conn, "/packets", "k", &reply).unwrap();
+        assert_eq!(reserve(&conn, "/packets", "k", 1002, 0, 2000).unwrap(), Reservation::Completed(reply));
+
+        // Once the window has passed the key is free again.
+        assert_eq!(reserve(&conn, "/packets", "k", 5000, 2000, 0).unwrap(), Reservation::Reserved);
+    }
+
+    #[test]
+    fn it_releases_keys_without_a_reply() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrations::apply_all(&conn).unwrap();
+
+        assert_eq!(reserve(&conn, "/packets", "k", 1000, 0, 0).unwrap(), Reservation::Reserved);
+        release(&conn, "/packets", "k").unwrap();
+        assert_eq!(reserve(&conn, "/packets", "k", 1001, 0, 0).unwrap(), Reservation::Reserved);
+
+        // A key left in progress, as when the server dies mid-upload, is
+        // reclaimed once its lease is up.
+        assert_eq!(reserve(&conn, "/packets", "k", 1500, 0, 1001).unwrap(), Reservation::InProgress);
+        assert_eq!(reserve(&conn, "/packets", "k", 1600, 0, 1002).unwrap(), Reservation::Reserved);
+    }
+
+    #[test]
+    fn it_scopes_keys_to_their_endpoint() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrations::apply_all(&conn).unwrap();
+
+        let reply = StoredReply { status: 200, content_type: "application/json".into(), body: "{}".into() };
+        assert_eq!(reserve(&conn, "/packets", "k", 1000, 0, 0).unwrap(), Reservation::Reserved);
+        complete(&conn, "/packets", "k", &reply).unwrap();
+        assert_eq!(reserve(&conn, "/packets/batch", "k", 1001, 0, 0).unwrap(), Reservation::Reserved);
+        assert_eq!(reserve(&conn, "/packets", "k", 1002, 0, 0).unwrap(), Reservation::Completed(reply));
+    }
+}
@@ -0,0 +1,114 @@
+use rusqlite::{params, OptionalExtension, Result, Row, Connection};
+use serde::Serialize;
+
+use crate::timestamp::Timestamp;
+
+use super::context::{TLM_LEVEL_0_TABLE, TLM_LEGAL_HOLD_LOG_TABLE};
+
+/// One entry in a packet's legal hold history.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct HoldEvent {
+    /// `placed` or `released`
+    pub action: String,
+    /// Who placed or released the hold.
+    pub actor: String,
+    pub reason: Option<String>,
+    pub at: Timestamp,
+}
+
+/// Puts packet `uuid` under legal hold, on `actor`'s say-so.
+///
+/// Returns `None` if there's no such packet (or it's in the trash), and
+/// `Some(false)` if it was already held.
+pub fn place(conn: &Connection, uuid: &str, actor: &str, reason: Option<&str>, now: i64) -> Result<Option<bool>> {
+    set_held(conn, uuid, true, actor, reason, now)
+}
+
+/// Lifts the legal hold on packet `uuid`, on `actor`'s say-so.
+///
+/// Returns `None` if there's no such packet, and `Some(false)` if it wasn't held.
+pub fn release(conn: &Connection, uuid: &str, actor: &str, reason: Option<&str>, now: i64) -> Result<Option<bool>> {
+    set_held(conn, uuid, false, actor, reason, now)
+}
+
+fn set_held(conn: &Connection, uuid: &str, held: bool, actor: &str, reason: Option<&str>, now: i64) -> Result<Option<bool>> {
+    let tx = conn.unchecked_transaction()?;
+
+    // Writing first takes the write lock straight away, rather than upgrading
+    // to it after a read, which another writer can beat us to.
+    let changed = tx.execute(
+        &format!("update {} set held = ?2 where uuid = ?1 and deleted_at is null and held != ?2", TLM_LEVEL_0_TABLE),
+        params![uuid, held],
+    )?;
+    if changed == 0 {
+        let exists = tx
+            .query_row(
+                &format!("select 1 from {} where uuid = ?1 and deleted_at is null", TLM_LEVEL_0_TABLE),
+                params![uuid],
+                |_| Ok(()),
+            )
+            .optional()?;
+        return Ok(exists.map(|()| false));
+    }
+
+    tx.execute(
+        &format!(
+            "insert into {} (uuid, action, actor, reason, createdate) values (?1, ?2, ?3, ?4, ?5)",
+            TLM_LEGAL_HOLD_LOG_TABLE,
+        ),
+        params![uuid, if held { "placed" } else { "released" }, actor, reason, now],
+    )?;
+    tx.commit()?;
+
+    Ok(Some(true))
+}
+
+/// Every hold placed on or released from packet `uuid`, oldest first.
+pub fn history(conn: &Connection, uuid: &str) -> Result<Vec<HoldEvent>> {
+    let sql = format!(
+        "select action, actor, reason, createdate from {} where uuid = ?1 order by id",
+        TLM_LEGAL_HOLD_LOG_TABLE,
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params![uuid], event_from_row)?;
+
+    rows.collect()
+}
+
+fn event_from_row(row: &Row) -> Result<HoldEvent> {
+    Ok(HoldEvent {
+        action: row.get(0)?,
+        actor: row.get(1)?,
+        reason: row.get(2)?,
+        at: row.get(3)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migrations;
+
+    #[test]
+    fn it_places_and_releases_holds() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrations::apply_all(&conn).unwrap();
+        conn.execute(
+            "insert into level_0 (uuid, createdate, received_at, metadata, packet) values ('uuid-0', 0, 0, '{}', x'00')",
+            [],
+        )
+        .unwrap();
+
+        assert_eq!(place(&conn, "uuid-0", "flight-dynamics", Some("anomaly review"), 1000).unwrap(), Some(true));
+        assert_eq!(place(&conn, "uuid-0", "someone-else", None, 2000).unwrap(), Some(false));
+        assert_eq!(release(&conn, "uuid-0", "flight-dynamics", None, 3000).unwrap(), Some(true));
+        assert_eq!(release(&conn, "uuid-0", "flight-dynamics", None, 4000).unwrap(), Some(false));
+        assert_eq!(place(&conn, "uuid-9", "flight-dynamics", None, 5000).unwrap(), None);
+
+        let events = history(&conn, "uuid-0").unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].action, "placed");
+        assert_eq!(events[0].reason.as_deref(), Some("anomaly review"));
+        assert_eq!(events[1].at, Timestamp(3000));
+    }
+}
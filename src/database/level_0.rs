@@ -0,0 +1,552 @@
+use rusqlite::{params, params_from_iter, DatabaseName, OptionalExtension, Result, Row, Connection};
+use rusqlite::types::{Type, Value};
+use serde::{Serialize, Deserialize};
+
+use crate::timestamp::Timestamp;
+
+use super::context::{TLM_LEVEL_0_TABLE, TLM_LEVEL_0_FTS_TABLE};
+use super::{metadata_index, tag};
+
+/// Default number of packets returned by `list`.
+pub const DEFAULT_LIST_LIMIT: u32 = 100;
+/// Upper bound on the number of packets returned by `list`.
+pub const MAX_LIST_LIMIT: u32 = 1000;
+
+/// Filters for listing `level_0` packets.
+///
+/// Paging is keyset-based: pass the `next` cursor of one page as `after`
+/// to get the following one.
+#[derive(Deserialize, Debug, Default)]
+pub struct ListFilter {
+    /// Only packets received at or after this time.
+    pub from: Option<Timestamp>,
+    /// Only packets received before this time.
+    pub to: Option<Timestamp>,
+    /// Only packets observed at or after this time.
+    pub observed_from: Option<Timestamp>,
+    /// Only packets observed before this time.
+    pub observed_to: Option<Timestamp>,
+    /// Only packets whose `filetype` matches exactly.
+    pub filetype: Option<String>,
+    /// Only packets whose `filename` matches exactly.
+    pub filename: Option<String>,
+    /// Only packets with at least one of these comma-separated tags.
+    pub tags_any: Option<String>,
+    /// Only packets with every one of these comma-separated tags.
+    pub tags_all: Option<String>,
+    /// List the trash instead: packets that have been deleted but not yet purged.
+    #[serde(default)]
+    pub trashed: bool,
+    /// Only packets with an id greater than this cursor.
+    pub after: Option<i64>,
+    pub limit: Option<u32>,
+    /// Only packets whose metadata passes every one of these, e.g. from
+    /// `meta.instrument=cam2&meta.exposure>0.5`. See `MetaFilter::parse`.
+    #[serde(skip)]
+    pub meta: Vec<MetaFilter>,
+}
+
+impl ListFilter {
+    /// The page size, defaulted and clamped to `MAX_LIST_LIMIT`.
+    pub fn limit(&self) -> u32 {
+        self.limit.unwrap_or(DEFAULT_LIST_LIMIT).min(MAX_LIST_LIMIT)
+    }
+}
+
+/// Splits a comma-separated list of tags, dropping blanks and repeats.
+fn split_tags(tags: &str) -> Vec<String> {
+    let mut split: Vec<String> = Vec::new();
+    for tag in tags.split(',').map(str::trim).filter(|tag| !tag.is_empty()) {
+        if !split.iter().any(|seen| seen == tag) {
+            split.push(tag.to_string());
+        }
+    }
+    split
+}
+
+/// A comparison against one field of a packet's metadata.
+#[derive(Debug, PartialEq)]
+pub struct MetaFilter {
+    /// Dotted path to the field, e.g. `camera.exposure`.
+    pub path: String,
+    pub op: MetaOp,
+    pub value: Value,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum MetaOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl MetaOp {
+    fn as_sql(self) -> &'static str {
+        match self {
+            MetaOp::Eq => "=",
+            MetaOp::Ne => "!=",
+            MetaOp::Lt => "<",
+            MetaOp::Le => "<=",
+            MetaOp::Gt => ">",
+            MetaOp::Ge => ">=",
+        }
+    }
+}
+
+impl MetaFilter {
+    /// Parses one query string pair as a metadata filter, or returns `None` if
+    /// its name isn't `meta.`-prefixed.
+    ///
+    /// The operator ends up split across the pair depending on where the `=`
+    /// falls: `meta.a=1` is `("meta.a", "1")`, `meta.a>=1` is `("meta.a>", "1")`
+    /// and `meta.a>1`, having no `=`, is `("meta.a>1", "")`. Values that parse
+    /// as numbers are compared as numbers, anything else as text.
+    pub fn parse(name: &str, value: &str) -> Option<Result<MetaFilter, String>> {
+        let rest = name.strip_prefix("meta.")?;
+
+        let expr = if value.is_empty() && rest.contains(['<', '>']) {
+            rest.to_string()
+        } else {
+            format!("{}={}", rest, value)
+        };
+        let at = expr.find(['=', '!', '<', '>']).unwrap_or(expr.len());
+        let (path, expr) = expr.split_at(at);
+
+        let ops = [
+            (">=", MetaOp::Ge),
+            ("<=", MetaOp::Le),
+            ("!=", MetaOp::Ne),
+            ("=", MetaOp::Eq),
+            (">", MetaOp::Gt),
+            ("<", MetaOp::Lt),
+        ];
+        let (op, value) = match ops.iter().find(|(symbol, _)| expr.starts_with(symbol)) {
+            Some((symbol, op)) => (*op, &expr[symbol.len()..]),
+            None => return Some(Err(format!("no comparison in meta filter {:?}", name))),
+        };
+
+        if !is_metadata_path(path) {
+            return Some(Err(format!("{:?} is not a metadata path", path)));
+        }
+
+        let value = if let Ok(integer) = value.parse::<i64>() {
+            Value::Integer(integer)
+        } else if let Ok(real) = value.parse::<f64>() {
+            Value::Real(real)
+        } else {
+            Value::Text(value.to_string())
+        };
+
+        Some(Ok(MetaFilter { path: path.to_string(), op, value }))
+    }
+}
+
+/// Whether `path` is a dotted path of plain names, which is all metadata
+/// filters and indexes accept. Being that plain is what lets it be written
+/// straight into SQL.
+pub fn is_metadata_path(path: &str) -> bool {
+    path.split('.').all(|name| {
+        !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+/// A full-text search of packet metadata.
+#[derive(Deserialize, Debug)]
+pub struct SearchQuery {
+    /// An FTS5 query, e.g. `telemetry`, `filename:pass*` or `"sun sensor" OR imu`.
+    pub q: String,
+    pub limit: Option<u32>,
+}
+
+impl SearchQuery {
+    /// The number of matches to return, defaulted and clamped to `MAX_LIST_LIMIT`.
+    pub fn limit(&self) -> u32 {
+        self.limit.unwrap_or(DEFAULT_LIST_LIMIT).min(MAX_LIST_LIMIT)
+    }
+}
+
+/// A `level_0` row without its packet bytes.
+#[derive(Serialize, Debug)]
+pub struct PacketSummary {
+    #[serde(skip)]
+    pub id: i64,
+    pub uuid: String,
+    pub createdate: i64,
+    pub metadata: serde_json::Value,
+    pub size: i64,
+    /// Hex sha256 of the packet bytes, if it was stored with one.
+    pub sha256: Option<String>,
+    /// When the server received the packet.
+    pub received_at: Timestamp,
+    /// When the packet's data was observed, if the client said.
+    pub observed_at: Option<Timestamp>,
+    /// When the packet was moved to the trash, if it has been.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<Timestamp>,
+    /// Whether the packet is under legal hold, and can't be deleted or changed.
+    pub held: bool,
+    /// The packet's tags, in name order.
+    pub tags: Vec<String>,
+}
+
+/// What came of asking to change a packet.
+#[derive(Debug, PartialEq)]
+pub enum Change {
+    Made,
+    /// There's no such packet (or it's in the trash).
+    Missing,
+    /// The packet is under legal hold.
+    Held,
+}
+
+/// Lists packets matching `filter`, oldest first.
+pub fn list(conn: &Connection, filter: &ListFilter) -> Result<Vec<PacketSummary>> {
+    let mut clauses: Vec<String> = Vec::new();
+    let mut values: Vec<Value> = Vec::new();
+
+    clauses.push(if filter.trashed { "deleted_at is not null" } else { "deleted_at is null" }.into());
+    if let Some(after) = filter.after {
+        clauses.push("id > ?".into());
+        values.push(Value::Integer(after));
+    }
+    if let Some(from) = filter.from {
+        clauses.push("received_at >= ?".into());
+        values.push(Value::Integer(from.0));
+    }
+    if let Some(to) = filter.to {
+        clauses.push("received_at < ?".into());
+        values.push(Value::Integer(to.0));
+    }
+    if let Some(from) = filter.observed_from {
+        clauses.push("observed_at >= ?".into());
+        values.push(Value::Integer(from.0));
+    }
+    if let Some(to) = filter.observed_to {
+        clauses.push("observed_at < ?".into());
+        values.push(Value::Integer(to.0));
+    }
+    if let Some(filetype) = &filter.filetype {
+        clauses.push("filetype = ?".into());
+        values.push(Value::Text(filetype.clone()));
+    }
+    if let Some(filename) = &filter.filename {
+        clauses.push("filename = ?".into());
+        values.push(Value::Text(filename.clone()));
+    }
+    for (tags, all) in [(&filter.tags_any, false), (&filter.tags_all, true)] {
+        let tags = match tags {
+            Some(tags) => split_tags(tags),
+            None => continue,
+        };
+        if !tags.is_empty() {
+            clauses.push(format!("id in ({})", tag::tagged_ids(tags.len(), all)));
+            values.extend(tags.into_iter().map(Value::Text));
+        }
+    }
+
+    // Fields with a generated column of their own are compared on that, so its index can be used.
+    if !filter.meta.is_empty() {
+        let columns = metadata_index::columns(conn)?;
+        for meta in &filter.meta {
+            let field = match columns.get(&meta.path) {
+                Some(column) => format!("\"{}\"", column),
+                None => metadata_index::json_extract(&meta.path),
+            };
+            clauses.push(format!("{} {} ?", field, meta.op.as_sql()));
+            values.push(meta.value.clone());
+        }
+    }
+
+    values.push(Value::Integer(filter.limit().into()));
+
+    let sql = format!(
+        "select {} from {} where {} order by id limit ?",
+        summary_columns(), TLM_LEVEL_0_TABLE, clauses.join(" and "),
+    );
+
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params_from_iter(values), summary_from_row)?;
+
+    rows.collect()
+}
+
+/// A packet matching a search, with the metadata around the match.
+#[derive(Serialize, Debug)]
+pub struct SearchHit {
+    #[serde(flatten)]
+    pub packet: PacketSummary,
+    /// bm25 score; lower is a better match.
+    pub rank: f64,
+    /// The matching stretch of metadata, with the matched terms in `[` `]`.
+    pub snippet: String,
+}
+
+/// Searches packet metadata, best matches first. Packets in the trash are
+/// still indexed, but left out.
+pub fn search(conn: &Connection, query: &SearchQuery) -> Result<Vec<SearchHit>> {
+    let sql = format!(
+        "select {columns}, hits.hit_rank, hits.snippet from {table}
+        join (
+            select rowid as hit_id, rank as hit_rank, snippet({fts}, -1, '[', ']', '...', 16) as snippet
+            from {fts} where {fts} match ?1
+        ) hits on hits.hit_id = {table}.id
+        where {table}.deleted_at is null
+        order by hits.hit_rank limit ?2",
+        columns = summary_columns(),
+        table = TLM_LEVEL_0_TABLE,
+        fts = TLM_LEVEL_0_FTS_TABLE,
+    );
+
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params![query.q, query.limit()], |row| {
+        Ok(SearchHit {
+            packet: summary_from_row(row)?,
+            rank: row.get(11)?,
+            snippet: row.get(12)?,
+        })
+    })?;
+
+    rows.collect()
+}
+
+/// Looks up a single packet by uuid, unless it is in the trash.
+pub fn get(conn: &Connection, uuid: &str) -> Result<Option<PacketSummary>> {
+    let sql = format!("select {} from {} where uuid = ?1 and deleted_at is null", summary_columns(), TLM_LEVEL_0_TABLE);
+    conn.query_row(&sql, params![uuid], summary_from_row).optional()
+}
+
+/// Finds the uuid of a packet with the given sha256, if one is stored and
+/// not in the trash.
+pub fn find_by_sha256(conn: &Connection, sha256: &str) -> Result<Option<String>> {
+    let sql = format!("select uuid from {} where sha256 = ?1 and deleted_at is null order by id limit 1", TLM_LEVEL_0_TABLE);
+    conn.query_row(&sql, params![sha256], |row| row.get(0)).optional()
+}
+
+/// Moves a packet to the trash at `now`, unless it is under legal hold. It
+/// stays there, out of sight, until it is restored or purged.
+pub fn trash(conn: &Connection, uuid: &str, now: i64) -> Result<Change> {
+    let sql = format!("update {} set deleted_at = ?2 where uuid = ?1 and deleted_at is null and not held", TLM_LEVEL_0_TABLE);
+    if conn.execute(&sql, params![uuid, now])? > 0 {
+        return Ok(Change::Made);
+    }
+
+    unchanged(conn, uuid)
+}
+
+/// Why a change to packet `uuid` that needed it to be live and not held
+/// didn't happen.
+pub fn unchanged(conn: &Connection, uuid: &str) -> Result<Change> {
+    let sql = format!("select held from {} where uuid = ?1 and deleted_at is null", TLM_LEVEL_0_TABLE);
+    let held: Option<bool> = conn.query_row(&sql, params![uuid], |row| row.get(0)).optional()?;
+    Ok(if held == Some(true) { Change::Held } else { Change::Missing })
+}
+
+/// Takes a packet back out of the trash, returning whether it was in there.
+pub fn restore(conn: &Connection, uuid: &str) -> Result<bool> {
+    let sql = format!("update {} set deleted_at = null where uuid = ?1 and deleted_at is not null", TLM_LEVEL_0_TABLE);
+    let restored = conn.execute(&sql, params![uuid])?;
+    Ok(restored > 0)
+}
+
+/// Reads up to `buf.len()` bytes of packet `id`'s BLOB, starting at `offset`.
+pub fn read_packet_at(conn: &Connection, id: i64, buf: &mut [u8], offset: usize) -> Result<usize> {
+    let blob = conn.blob_open(DatabaseName::Main, TLM_LEVEL_0_TABLE, "packet", id, true)?;
+    blob.read_at(buf, offset)
+}
+
+/// Columns selected to build a `PacketSummary`, in `summary_from_row` order.
+/// The last is the packet's tags, as a JSON array.
+fn summary_columns() -> String {
+    format!(
+        "id, uuid, createdate, metadata, length(packet), sha256, received_at, observed_at, deleted_at, held, {}",
+        tag::names_json(&format!("{}.id", TLM_LEVEL_0_TABLE)),
+    )
+}
+
+fn summary_from_row(row: &Row) -> Result<PacketSummary> {
+    let metadata: String = row.get(3)?;
+    let tags: String = row.get(10)?;
+    Ok(PacketSummary {
+        id: row.get(0)?,
+        uuid: row.get(1)?,
+        createdate: row.get(2)?,
+        metadata: serde_json::from_str(&metadata).unwrap_or(serde_json::Value::String(metadata)),
+        size: row.get(4)?,
+        sha256: row.get(5)?,
+        received_at: row.get(6)?,
+        observed_at: row.get(7)?,
+        deleted_at: row.get(8)?,
+        held: row.get(9)?,
+        tags: serde_json::from_str(&tags)
+            .map_err(|error| rusqlite::Error::FromSqlConversionFailure(10, Type::Text, Box::new(error)))?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::params;
+
+    use super::*;
+    use crate::database::migrations;
+
+    fn test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        migrations::apply_all(&conn).unwrap();
+        for (i, filetype) in ["image", "log", "image"].iter().enumerate() {
+            conn.execute(
+                "insert into level_0 (uuid, createdate, received_at, observed_at, metadata, filename, filetype, packet)
+                values (?1, ?2, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    format!("uuid-{}", i),
+                    (i as i64) * 1000,
+                    // Observed in the reverse order to receipt.
+                    (3 - i as i64) * 100,
+                    format!(r#"{{"filename": "f{}.bin", "filetype": "{}"}}"#, i, filetype),
+                    format!("f{}.bin", i),
+                    filetype,
+                    vec![0u8; i + 1],
+                ],
+            )
+            .unwrap();
+        }
+        conn
+    }
+
+    #[test]
+    fn it_pages_with_a_cursor() {
+        let conn = test_db();
+        let first = list(&conn, &ListFilter { limit: Some(2), ..Default::default() }).unwrap();
+        assert_eq!(first.len(), 2);
+        assert_eq!(first[1].size, 2);
+
+        let rest = list(&conn, &ListFilter { after: Some(first[1].id), ..Default::default() }).unwrap();
+        assert_eq!(rest.len(), 1);
+        assert_eq!(rest[0].uuid, "uuid-2");
+    }
+
+    #[test]
+    fn it_filters_by_metadata_and_date() {
+        let conn = test_db();
+        let images = list(&conn, &ListFilter { filetype: Some("image".into()), ..Default::default() }).unwrap();
+        assert_eq!(images.len(), 2);
+
+        let late_images = list(&conn, &ListFilter {
+            filetype: Some("image".into()),
+            from: Some(Timestamp(1000)),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(late_images.len(), 1);
+        assert_eq!(late_images[0].metadata["filename"], "f2.bin");
+    }
+
+    #[test]
+    fn it_filters_by_observation_time() {
+        let conn = test_db();
+        let early = list(&conn, &ListFilter { observed_to: Some(Timestamp(150)), ..Default::default() }).unwrap();
+        assert_eq!(early.len(), 1);
+        assert_eq!(early[0].uuid, "uuid-2");
+        assert_eq!(early[0].received_at, Timestamp(2000));
+    }
+
+    #[test]
+    fn it_parses_meta_filters() {
+        let parse = |name: &str, value: &str| MetaFilter::parse(name, value).unwrap();
+        assert_eq!(
+            parse("meta.instrument", "cam2").unwrap(),
+            MetaFilter { path: "instrument".into(), op: MetaOp::Eq, value: Value::Text("cam2".into()) },
+        );
+        assert_eq!(parse("meta.camera.exposure>0.5", "").unwrap().op, MetaOp::Gt);
+        assert_eq!(parse("meta.camera.exposure>0.5", "").unwrap().value, Value::Real(0.5));
+        assert_eq!(parse("meta.gain<", "3").unwrap().op, MetaOp::Le);
+        assert_eq!(parse("meta.gain!", "3").unwrap().value, Value::Integer(3));
+
+        assert!(MetaFilter::parse("filetype", "image").is_none());
+        assert!(parse("meta.", "x").is_err());
+        assert!(parse("meta.a'b", "x").is_err());
+    }
+
+    #[test]
+    fn it_filters_by_metadata_fields() {
+        let conn = test_db();
+        conn.execute("update level_0 set metadata = json_set(metadata, '$.exposure', id * 0.25)", []).unwrap();
+        let filter = || ListFilter {
+            meta: vec![
+                MetaFilter::parse("meta.filetype", "image").unwrap().unwrap(),
+                MetaFilter::parse("meta.exposure>0.5", "").unwrap().unwrap(),
+            ],
+            ..Default::default()
+        };
+
+        let matches = list(&conn, &filter()).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].uuid, "uuid-2");
+
+        metadata_index::create(&conn, "exposure", 0).unwrap();
+        assert_eq!(list(&conn, &filter()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn it_filters_by_tags() {
+        let conn = test_db();
+        let tags = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+        tag::add(&conn, "uuid-0", &tags(&["anomaly", "calibration"]), 0).unwrap();
+        tag::add(&conn, "uuid-2", &tags(&["anomaly"]), 0).unwrap();
+        let tagged = |any: Option<&str>, all: Option<&str>| {
+            let filter = ListFilter { tags_any: any.map(String::from), tags_all: all.map(String::from), ..Default::default() };
+            list(&conn, &filter).unwrap().into_iter().map(|packet| packet.uuid).collect::<Vec<_>>()
+        };
+
+        assert_eq!(tagged(Some("calibration, anomaly"), None), ["uuid-0", "uuid-2"]);
+        assert_eq!(tagged(None, Some("calibration,anomaly,anomaly")), ["uuid-0"]);
+        assert_eq!(tagged(Some("anomaly"), Some("calibration")), ["uuid-0"]);
+        assert!(tagged(Some("unknown"), None).is_empty());
+        assert_eq!(get(&conn, "uuid-0").unwrap().unwrap().tags, ["anomaly", "calibration"]);
+        assert!(get(&conn, "uuid-1").unwrap().unwrap().tags.is_empty());
+    }
+
+    #[test]
+    fn it_searches_metadata() {
+        let conn = test_db();
+        let search = |q: &str| search(&conn, &SearchQuery { q: q.into(), limit: None }).unwrap();
+
+        let hits = search("filetype:image");
+        assert_eq!(hits.len(), 2);
+        assert!(hits[0].snippet.contains("[image]"));
+
+        assert_eq!(search("f1").len(), 1);
+        conn.execute("update level_0 set metadata = '{\"note\": \"renamed\"}' where uuid = 'uuid-1'", []).unwrap();
+        assert_eq!(search("renamed")[0].packet.uuid, "uuid-1");
+
+        assert_eq!(trash(&conn, "uuid-1", 5000).unwrap(), Change::Made);
+        assert!(search("renamed").is_empty());
+    }
+
+    #[test]
+    fn it_gets_reads_and_trashes_by_uuid() {
+        let conn = test_db();
+        let packet = get(&conn, "uuid-1").unwrap().unwrap();
+        let mut buf = [1u8; 4];
+        assert_eq!(read_packet_at(&conn, packet.id, &mut buf, 0).unwrap(), 2);
+        assert_eq!(&buf[..2], &[0, 0]);
+
+        assert_eq!(trash(&conn, "uuid-1", 5000).unwrap(), Change::Made);
+        assert_eq!(trash(&conn, "uuid-1", 6000).unwrap(), Change::Missing);
+        assert!(get(&conn, "uuid-1").unwrap().is_none());
+        assert_eq!(list(&conn, &ListFilter::default()).unwrap().len(), 2);
+
+        let trashed = list(&conn, &ListFilter { trashed: true, ..Default::default() }).unwrap();
+        assert_eq!(trashed[0].deleted_at, Some(Timestamp(5000)));
+
+        assert!(restore(&conn, "uuid-1").unwrap());
+        assert!(!restore(&conn, "uuid-1").unwrap());
+        assert!(get(&conn, "uuid-1").unwrap().is_some());
+
+        conn.execute("update level_0 set held = 1 where uuid = 'uuid-1'", []).unwrap();
+        assert_eq!(trash(&conn, "uuid-1", 7000).unwrap(), Change::Held);
+        assert!(get(&conn, "uuid-1").unwrap().unwrap().held);
+    }
+}
@@ -0,0 +1,167 @@
+use rusqlite::{params, OptionalExtension, Result, Row, Connection};
+use serde::Serialize;
+
+use crate::timestamp::Timestamp;
+
+use super::context::{TLM_LEVEL_0_TABLE, TLM_LEVEL_0_METADATA_HISTORY_TABLE};
+
+/// One version of a packet's metadata.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct MetadataRevision {
+    /// 1 for the metadata the packet was uploaded with, counting up from there.
+    pub revision: i64,
+    pub metadata: serde_json::Value,
+    /// Who wrote this version; `None` for the upload.
+    pub author: Option<String>,
+    /// When this version was written.
+    pub at: Timestamp,
+}
+
+/// The current metadata of a packet, and whether it is under legal hold.
+pub struct Current {
+    pub revision: MetadataRevision,
+    pub held: bool,
+}
+
+/// New metadata for a packet, with the fields `level_0` keeps in columns of
+/// their own pulled out.
+pub struct Revision<'a> {
+    pub metadata: &'a serde_json::Value,
+    pub filename: &'a str,
+    pub filetype: &'a str,
+    pub observed_at: Option<Timestamp>,
+    pub author: &'a str,
+    pub at: i64,
+}
+
+/// The current metadata of packet `uuid`, unless there's no such packet or
+/// it's in the trash.
+pub fn current(conn: &Connection, uuid: &str) -> Result<Option<Current>> {
+    let sql = format!(
+        "select metadata_revision, metadata, metadata_author, coalesce(metadata_updated_at, received_at), held
+        from {} where uuid = ?1 and deleted_at is null",
+        TLM_LEVEL_0_TABLE,
+    );
+    conn.query_row(&sql, params![uuid], |row| {
+        Ok(Current { revision: revision_from_row(row)?, held: row.get(4)? })
+    })
+    .optional()
+}
+
+/// Replaces packet `uuid`'s `current` metadata with `revision`, keeping the
+/// current version in its history. The caller holds a write transaction.
+pub fn revise(conn: &Connection, uuid: &str, current: &MetadataRevision, revision: &Revision) -> Result<MetadataRevision> {
+    conn.execute(
+        &format!(
+            "insert into {} (uuid, revision, metadata, author, createdate) values (?1, ?2, ?3, ?4, ?5)",
+            TLM_LEVEL_0_METADATA_HISTORY_TABLE,
+        ),
+        params![uuid, current.revision, current.metadata.to_string(), current.author, current.at],
+    )?;
+
+    let next = current.revision + 1;
+    conn.execute(
+        &format!(
+            "update {} set metadata = ?2, filename = ?3, filetype = ?4, observed_at = ?5,
+                metadata_revision = ?6, metadata_author = ?7, metadata_updated_at = ?8
+            where uuid = ?1",
+            TLM_LEVEL_0_TABLE,
+        ),
+        params![
+            uuid,
+            revision.metadata.to_string(),
+            revision.filename,
+            revision.filetype,
+            revision.observed_at,
+            next,
+            revision.author,
+            revision.at,
+        ],
+    )?;
+
+    Ok(MetadataRevision {
+        revision: next,
+        metadata: revision.metadata.clone(),
+        author: Some(revision.author.to_string()),
+        at: Timestamp(revision.at),
+    })
+}
+
+/// Every version of packet `uuid`'s metadata, oldest first, ending with the
+/// current one. `None` if there's no such packet or it's in the trash.
+pub fn history(conn: &Connection, uuid: &str) -> Result<Option<Vec<MetadataRevision>>> {
+    let current = match current(conn, uuid)? {
+        Some(current) => current.revision,
+        None => return Ok(None),
+    };
+
+    let sql = format!(
+        "select revision, metadata, author, createdate from {} where uuid = ?1 order by revision",
+        TLM_LEVEL_0_METADATA_HISTORY_TABLE,
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let mut revisions = stmt.query_map(params![uuid], revision_from_row)?.collect::<Result<Vec<_>>>()?;
+    revisions.push(current);
+
+    Ok(Some(revisions))
+}
+
+/// Forgets packet `uuid`'s history, once the packet itself is gone.
+pub fn forget(conn: &Connection, uuid: &str) -> Result<()> {
+    let sql = format!("delete from {} where uuid = ?1", TLM_LEVEL_0_METADATA_HISTORY_TABLE);
+    conn.execute(&sql, params![uuid])?;
+    Ok(())
+}
+
+fn revision_from_row(row: &Row) -> Result<MetadataRevision> {
+    let metadata: String = row.get(1)?;
+    Ok(MetadataRevision {
+        revision: row.get(0)?,
+        metadata: serde_json::from_str(&metadata).unwrap_or(serde_json::Value::String(metadata)),
+        author: row.get(2)?,
+        at: row.get(3)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::database::migrations;
+
+    #[test]
+    fn it_keeps_every_previous_version() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrations::apply_all(&conn).unwrap();
+        conn.execute(
+            "insert into level_0 (uuid, createdate, received_at, metadata, filename, filetype, packet)
+            values ('uuid-0', 1000, 1000, '{\"filename\": \"a\", \"filetype\": \"log\"}', 'a', 'log', x'00')",
+            [],
+        )
+        .unwrap();
+
+        let metadata = json!({"filename": "b", "filetype": "log"});
+        let current = current(&conn, "uuid-0").unwrap().unwrap().revision;
+        let revised = revise(&conn, "uuid-0", &current, &Revision {
+            metadata: &metadata,
+            filename: "b",
+            filetype: "log",
+            observed_at: None,
+            author: "ops",
+            at: 2000,
+        })
+        .unwrap();
+        assert_eq!(revised.revision, 2);
+
+        let revisions = history(&conn, "uuid-0").unwrap().unwrap();
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(revisions[0].metadata["filename"], "a");
+        assert_eq!(revisions[0].at, Timestamp(1000));
+        assert_eq!(revisions[1], revised);
+
+        let filename: String = conn.query_row("select filename from level_0", [], |row| row.get(0)).unwrap();
+        assert_eq!(filename, "b");
+        assert!(history(&conn, "uuid-9").unwrap().is_none());
+    }
+}
@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+
+use rusqlite::{params, OptionalExtension, Result, Row, Connection};
+use serde::Serialize;
+
+use super::context::{TLM_LEVEL_0_TABLE, TLM_METADATA_INDEX_TABLE};
+
+/// A metadata path that has its own indexed generated column on `level_0`.
+#[derive(Serialize, Debug)]
+pub struct MetadataIndex {
+    pub path: String,
+    pub column_name: String,
+    pub createdate: i64,
+}
+
+/// Lists every indexed metadata path.
+pub fn list(conn: &Connection) -> Result<Vec<MetadataIndex>> {
+    let sql = format!("select path, column_name, createdate from {} order by path", TLM_METADATA_INDEX_TABLE);
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map([], index_from_row)?;
+
+    rows.collect()
+}
+
+/// Maps each indexed metadata path to its column on `level_0`.
+pub fn columns(conn: &Connection) -> Result<HashMap<String, String>> {
+    let sql = format!("select path, column_name from {}", TLM_METADATA_INDEX_TABLE);
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+    rows.collect()
+}
+
+/// Adds a generated column for metadata `path` to `level_0`, and an index on
+/// it, unless it already has one. Either way, returns the path's index.
+///
+/// `path` must pass `level_0::is_metadata_path`, as it is written into the DDL.
+pub fn create(conn: &Connection, path: &str, now: i64) -> Result<MetadataIndex> {
+    let existing = format!("select path, column_name, createdate from {} where path = ?1", TLM_METADATA_INDEX_TABLE);
+    if let Some(index) = conn.query_row(&existing, params![path], index_from_row).optional()? {
+        return Ok(index);
+    }
+
+    // Quoted, the dots in a path are fine in a column name and can't collide with another path's.
+    let column_name = format!("meta.{}", path);
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute_batch(&format!(
+        "ALTER TABLE {table} ADD COLUMN \"{column}\" GENERATED ALWAYS AS ({expr}) VIRTUAL;
+        CREATE INDEX IF NOT EXISTS \"{table}_{column}\" ON {table} (\"{column}\");",
+        table = TLM_LEVEL_0_TABLE,
+        column = column_name,
+        expr = json_extract(path),
+    ))?;
+    tx.execute(
+        &format!("insert into {} (path, column_name, createdate) values (?1, ?2, ?3)", TLM_METADATA_INDEX_TABLE),
+        params![path, column_name, now],
+    )?;
+    tx.commit()?;
+
+    Ok(MetadataIndex { path: path.to_string(), column_name, createdate: now })
+}
+
+/// The SQL expression for metadata `path` of a `level_0` row. Generated
+/// columns and filters without one share it, so they always agree.
+pub fn json_extract(path: &str) -> String {
+    format!("json_extract(metadata, '$.{}')", path)
+}
+
+fn index_from_row(row: &Row) -> Result<MetadataIndex> {
+    Ok(MetadataIndex {
+        path: row.get(0)?,
+        column_name: row.get(1)?,
+        createdate: row.get(2)?,
+    })
+}
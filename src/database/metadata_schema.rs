@@ -0,0 +1,79 @@
+use rusqlite::{params, OptionalExtension, Result, Row, Connection};
+use serde::Serialize;
+
+use super::context::TLM_METADATA_SCHEMA_TABLE;
+
+/// The JSON Schema registered for a `filetype`.
+#[derive(Serialize, Debug)]
+pub struct MetadataSchema {
+    pub filetype: String,
+    pub createdate: i64,
+    pub schema: serde_json::Value,
+}
+
+/// Lists every registered schema, by filetype.
+pub fn list(conn: &Connection) -> Result<Vec<MetadataSchema>> {
+    let sql = format!("select filetype, createdate, schema from {} order by filetype", TLM_METADATA_SCHEMA_TABLE);
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map([], schema_from_row)?;
+
+    rows.collect()
+}
+
+/// Looks up the schema registered for `filetype`, if there is one.
+pub fn get(conn: &Connection, filetype: &str) -> Result<Option<MetadataSchema>> {
+    let sql = format!("select filetype, createdate, schema from {} where filetype = ?1", TLM_METADATA_SCHEMA_TABLE);
+    conn.query_row(&sql, params![filetype], schema_from_row).optional()
+}
+
+/// Registers `schema` for `filetype`, replacing any schema it already had.
+pub fn put(conn: &Connection, filetype: &str, schema: &serde_json::Value, now: i64) -> Result<()> {
+    conn.execute(
+        &format!("insert or replace into {} (filetype, createdate, schema) values (?1, ?2, ?3)", TLM_METADATA_SCHEMA_TABLE),
+        params![filetype, now, schema.to_string()],
+    )?;
+    Ok(())
+}
+
+/// Drops the schema for `filetype`, returning whether there was one.
+pub fn delete(conn: &Connection, filetype: &str) -> Result<bool> {
+    let sql = format!("delete from {} where filetype = ?1", TLM_METADATA_SCHEMA_TABLE);
+    let deleted = conn.execute(&sql, params![filetype])?;
+    Ok(deleted > 0)
+}
+
+fn schema_from_row(row: &Row) -> Result<MetadataSchema> {
+    let schema: String = row.get(2)?;
+    Ok(MetadataSchema {
+        filetype: row.get(0)?,
+        createdate: row.get(1)?,
+        schema: serde_json::from_str(&schema).unwrap_or(serde_json::Value::String(schema)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::database::migrations;
+
+    #[test]
+    fn it_replaces_and_deletes_schemas() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrations::apply_all(&conn).unwrap();
+
+        put(&conn, "image", &json!({"required": ["width"]}), 1000).unwrap();
+        put(&conn, "image", &json!({"required": ["height"]}), 2000).unwrap();
+        put(&conn, "log", &json!({}), 3000).unwrap();
+
+        let image = get(&conn, "image").unwrap().unwrap();
+        assert_eq!(image.createdate, 2000);
+        assert_eq!(image.schema["required"][0], "height");
+        assert_eq!(list(&conn).unwrap().len(), 2);
+
+        assert!(delete(&conn, "image").unwrap());
+        assert!(!delete(&conn, "image").unwrap());
+        assert!(get(&conn, "image").unwrap().is_none());
+    }
+}
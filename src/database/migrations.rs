@@ -1,41 +1,89 @@
-use std::error::Error;
-use std::path::Path;
-use rusqlite::{params, Connection};
+use rusqlite::Connection;
 use barrel::backend::Sqlite;
 use barrel::{types, Migration};
 use crate::errors::ServerError;
-use crate::util::now;
 use super::context::{
-    TLM_DB,
     TLM_LEVEL_0_TABLE,
+    TLM_LEVEL_0_FTS_TABLE,
+    TLM_LEVEL_0_METADATA_HISTORY_TABLE,
     TLM_SINGLE_VALUE_TABLE,
-}}
+    TLM_SINGLE_VALUE_HISTORY_TABLE,
+    TLM_IDEMPOTENCY_KEY_TABLE,
+    TLM_METADATA_SCHEMA_TABLE,
+    TLM_METADATA_INDEX_TABLE,
+    TLM_RETENTION_LOG_TABLE,
+    TLM_LEGAL_HOLD_LOG_TABLE,
+    TLM_TAG_TABLE,
+    TLM_LEVEL_0_TAG_TABLE,
+};
+
+/// tlm.db migrations, in the order they are applied.
+///
+/// The position of a migration in this list is the schema version it brings
+/// the db up to (stored in `PRAGMA user_version`), so only ever append.
+const TLM_DB_MIGRATIONS: &[fn(&mut Migration)] = &[
+    initial_tlm_db,
+    index_level_0_createdate,
+    add_level_0_sha256,
+    index_level_0_sha256,
+    create_idempotency_key_table,
+    promote_level_0_metadata_fields,
+    create_metadata_schema_table,
+    create_level_0_fts,
+    create_metadata_index_table,
+    add_level_0_timestamps,
+    create_retention_log_table,
+    add_level_0_deleted_at,
+    add_level_0_legal_hold,
+    create_level_0_metadata_history,
+    create_tag_tables,
+    add_single_value_types,
+    add_single_value_revision,
+    add_single_value_namespaces,
+    scope_idempotency_keys,
+];
 
 /// Up-to-date db
 pub fn apply_all(conn: &Connection) -> Result<(), ServerError> {
     // apply migrations to tlm.db
-    initial_tlm_db(conn)
+    apply(conn, TLM_DB_MIGRATIONS)
 
     // apply migrations to other.db
     // etc
     // etc
 }
 
-/// Initial tlm.db migration
-fn initial_tlm_db(conn: &Connection) -> Result<(), ServerError> {
-    let mut m = Migration::new();
+/// Applies every migration in `migrations` newer than the db's `user_version`.
+fn apply(conn: &Connection, migrations: &[fn(&mut Migration)]) -> Result<(), ServerError> {
+    let version: usize = conn
+        .pragma_query_value(None, "user_version", |row| row.get(0))
+        .map_err(ServerError::Rusqlite)?;
 
-    create_initial_tlm_level_0_table(&mut m);
-    create_initial_tlm_single_value_table(&mut m);
+    for (i, migration) in migrations.iter().enumerate().skip(version) {
+        let mut m = Migration::new();
+        migration(&mut m);
 
-    conn.execute_batch(m.make::<Sqlite>().as_str()); // ?
+        // Each migration and its version bump land together or not at all.
+        conn.execute_batch(&format!(
+            "BEGIN; {} PRAGMA user_version = {}; COMMIT;",
+            m.make::<Sqlite>(),
+            i + 1,
+        ))
+        .map_err(ServerError::Rusqlite)?;
+    }
 
     Ok(())
 }
 
+/// Initial tlm.db migration
+fn initial_tlm_db(m: &mut Migration) {
+    create_initial_tlm_level_0_table(m);
+    create_initial_tlm_single_value_table(m);
+}
+
 /// Creates the `level_0` table in the database.
 fn create_initial_tlm_level_0_table(m: &mut Migration) {
-    m.create_table(TLM_LEVEL_0_TABLE, |t| {
+    m.create_table_if_not_exists(TLM_LEVEL_0_TABLE, |t| {
         t.add_column(
             "id",
             types::integer()
@@ -52,8 +100,379 @@ fn create_initial_tlm_level_0_table(m: &mut Migration) {
 
 /// Creates the `single_value` table in the database.
 fn create_initial_tlm_single_value_table(m: &mut Migration) {
-    m.create_table(TLM_SINGLE_VALUE_TABLE, |t| {
+    m.create_table_if_not_exists(TLM_SINGLE_VALUE_TABLE, |t| {
+        t.add_column("name", types::text().nullable(false).unique(true));
+        t.add_column("value", types::text().nullable(false));
+    });
+}
+
+/// Indexes `level_0.createdate` so listing by date range doesn't scan the table.
+fn index_level_0_createdate(m: &mut Migration) {
+    m.inject_custom(format!(
+        "CREATE INDEX IF NOT EXISTS level_0_createdate ON {} (createdate)",
+        TLM_LEVEL_0_TABLE,
+    ));
+}
+
+/// Adds a `sha256` hex digest of each packet's bytes to `level_0`.
+///
+/// Rows stored before this migration have no digest.
+fn add_level_0_sha256(m: &mut Migration) {
+    m.change_table(TLM_LEVEL_0_TABLE, |t| {
+        t.add_column("sha256", types::text().nullable(true));
+    });
+}
+
+/// Indexes `level_0.sha256` so re-sent packets can be found by content.
+fn index_level_0_sha256(m: &mut Migration) {
+    m.inject_custom(format!(
+        "CREATE INDEX IF NOT EXISTS level_0_sha256 ON {} (sha256)",
+        TLM_LEVEL_0_TABLE,
+    ));
+}
+
+/// Creates the `idempotency_key` table, which remembers the reply to each
+/// upload sent with an `Idempotency-Key` so retries get the same reply.
+///
+/// `status`, `content_type` and `body` stay null while the upload is running.
+fn create_idempotency_key_table(m: &mut Migration) {
+    m.create_table_if_not_exists(TLM_IDEMPOTENCY_KEY_TABLE, |t| {
+        t.add_column("key", types::text().nullable(false).unique(true));
+        t.add_column("createdate", types::integer().nullable(false));
+        t.add_column("status", types::integer().nullable(true));
+        t.add_column("content_type", types::text().nullable(true));
+        t.add_column("body", types::text().nullable(true));
+    });
+    m.inject_custom(format!(
+        "CREATE INDEX IF NOT EXISTS idempotency_key_createdate ON {} (createdate)",
+        TLM_IDEMPOTENCY_KEY_TABLE,
+    ));
+}
+
+/// Copies the core metadata fields out of `level_0.metadata` into indexed
+/// columns of their own, so listings can filter on them cheaply.
+fn promote_level_0_metadata_fields(m: &mut Migration) {
+    for field in &["filename", "filetype"] {
+        m.change_table(TLM_LEVEL_0_TABLE, move |t| {
+            t.add_column(*field, types::text().nullable(true));
+        });
+        m.inject_custom(format!(
+            "UPDATE {table} SET {field} = json_extract(metadata, '$.{field}');\
+             CREATE INDEX IF NOT EXISTS level_0_{field} ON {table} ({field})",
+            table = TLM_LEVEL_0_TABLE,
+            field = field,
+        ));
+    }
+}
+
+/// Creates the `metadata_schema` table, which holds the JSON Schema packet
+/// metadata of each `filetype` is checked against.
+fn create_metadata_schema_table(m: &mut Migration) {
+    m.create_table_if_not_exists(TLM_METADATA_SCHEMA_TABLE, |t| {
+        t.add_column("filetype", types::text().nullable(false).unique(true));
+        t.add_column("createdate", types::integer().nullable(false));
+        t.add_column("schema", types::text().nullable(false));
+    });
+}
+
+/// Creates `level_0_fts`, a full-text index over each packet's metadata, and
+/// the triggers that keep it in step with `level_0`, then fills it from the
+/// packets already stored.
+///
+/// The index holds no copy of the metadata; it reads it back out of `level_0`.
+fn create_level_0_fts(m: &mut Migration) {
+    m.inject_custom(format!(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS {fts} USING fts5(
+            filename, filetype, metadata, content='{table}', content_rowid='id'
+        );
+        CREATE TRIGGER IF NOT EXISTS level_0_fts_insert AFTER INSERT ON {table} BEGIN
+            INSERT INTO {fts} (rowid, filename, filetype, metadata)
+            VALUES (new.id, new.filename, new.filetype, new.metadata);
+        END;
+        CREATE TRIGGER IF NOT EXISTS level_0_fts_delete AFTER DELETE ON {table} BEGIN
+            INSERT INTO {fts} ({fts}, rowid, filename, filetype, metadata)
+            VALUES ('delete', old.id, old.filename, old.filetype, old.metadata);
+        END;
+        CREATE TRIGGER IF NOT EXISTS level_0_fts_update AFTER UPDATE OF filename, filetype, metadata ON {table} BEGIN
+            INSERT INTO {fts} ({fts}, rowid, filename, filetype, metadata)
+            VALUES ('delete', old.id, old.filename, old.filetype, old.metadata);
+            INSERT INTO {fts} (rowid, filename, filetype, metadata)
+            VALUES (new.id, new.filename, new.filetype, new.metadata);
+        END;
+        INSERT INTO {fts} ({fts}) VALUES ('rebuild')",
+        fts = TLM_LEVEL_0_FTS_TABLE,
+        table = TLM_LEVEL_0_TABLE,
+    ));
+}
+
+/// Creates the `metadata_index` table, which records the metadata paths that
+/// have been given an indexed generated column on `level_0`.
+fn create_metadata_index_table(m: &mut Migration) {
+    m.create_table_if_not_exists(TLM_METADATA_INDEX_TABLE, |t| {
+        t.add_column("path", types::text().nullable(false).unique(true));
+        t.add_column("column_name", types::text().nullable(false));
+        t.add_column("createdate", types::integer().nullable(false));
+    });
+}
+
+/// Splits when a packet was received from when it was observed: adds a
+/// `received_at` (ms since epoch) to `level_0`, filled from `createdate` for
+/// rows already stored, and an `observed_at` taken from the `observed_at` the
+/// client put in the metadata, if it did. Both are indexed.
+///
+/// `observed_at` may have been sent as ms since epoch or as ISO-8601 text.
+fn add_level_0_timestamps(m: &mut Migration) {
+    m.change_table(TLM_LEVEL_0_TABLE, |t| {
+        t.add_column("received_at", types::integer().nullable(true));
+    });
+    m.change_table(TLM_LEVEL_0_TABLE, |t| {
+        t.add_column("observed_at", types::integer().nullable(true));
+    });
+    m.inject_custom(format!(
+        "UPDATE {table} SET
+            received_at = createdate,
+            observed_at = CASE json_type(metadata, '$.observed_at')
+                WHEN 'integer' THEN json_extract(metadata, '$.observed_at')
+                WHEN 'text' THEN CAST(round((julianday(json_extract(metadata, '$.observed_at')) - 2440587.5) * 86400000) AS INTEGER)
+            END;
+        CREATE INDEX IF NOT EXISTS level_0_received_at ON {table} (received_at);
+        CREATE INDEX IF NOT EXISTS level_0_observed_at ON {table} (observed_at)",
+        table = TLM_LEVEL_0_TABLE,
+    ));
+}
+
+/// Creates the `retention_log` table, an audit trail of the packets purged by
+/// retention rules and which limit each one broke.
+fn create_retention_log_table(m: &mut Migration) {
+    m.create_table_if_not_exists(TLM_RETENTION_LOG_TABLE, |t| {
+        t.add_column(
+            "id",
+            types::integer()
+                .primary(true)
+                .increments(true)
+                .nullable(false),
+        );
+        t.add_column("purged_at", types::integer().nullable(false));
+        t.add_column("uuid", types::text().nullable(false));
+        t.add_column("filetype", types::text().nullable(true));
+        t.add_column("size", types::integer().nullable(false));
+        t.add_column("reason", types::text().nullable(false));
+    });
+    m.inject_custom(format!(
+        "CREATE INDEX IF NOT EXISTS retention_log_purged_at ON {} (purged_at)",
+        TLM_RETENTION_LOG_TABLE,
+    ));
+}
+
+/// Adds a `deleted_at` (ms since epoch) to `level_0`: packets with one are in
+/// the trash, hidden but restorable until they're purged.
+fn add_level_0_deleted_at(m: &mut Migration) {
+    m.change_table(TLM_LEVEL_0_TABLE, |t| {
+        t.add_column("deleted_at", types::integer().nullable(true));
+    });
+    m.inject_custom(format!(
+        "CREATE INDEX IF NOT EXISTS level_0_deleted_at ON {} (deleted_at)",
+        TLM_LEVEL_0_TABLE,
+    ));
+}
+
+/// Adds a `held` flag to `level_0`, for packets under legal hold that mustn't
+/// be deleted or changed, and the `legal_hold_log` of who placed and released
+/// each hold.
+fn add_level_0_legal_hold(m: &mut Migration) {
+    m.change_table(TLM_LEVEL_0_TABLE, |t| {
+        t.add_column("held", types::boolean().nullable(false).default(false));
+    });
+    m.create_table_if_not_exists(TLM_LEGAL_HOLD_LOG_TABLE, |t| {
+        t.add_column(
+            "id",
+            types::integer()
+                .primary(true)
+                .increments(true)
+                .nullable(false),
+        );
+        t.add_column("uuid", types::text().nullable(false));
+        t.add_column("action", types::text().nullable(false));
+        t.add_column("actor", types::text().nullable(false));
+        t.add_column("reason", types::text().nullable(true));
+        t.add_column("createdate", types::integer().nullable(false));
+    });
+    m.inject_custom(format!(
+        "CREATE INDEX IF NOT EXISTS legal_hold_log_uuid ON {} (uuid)",
+        TLM_LEGAL_HOLD_LOG_TABLE,
+    ));
+}
+
+/// Makes packet metadata editable: `level_0` gains the revision number, author
+/// and time of its current metadata, and `level_0_metadata_history` keeps
+/// every version it replaced.
+fn create_level_0_metadata_history(m: &mut Migration) {
+    m.change_table(TLM_LEVEL_0_TABLE, |t| {
+        t.add_column("metadata_revision", types::integer().nullable(false).default(1));
+    });
+    m.change_table(TLM_LEVEL_0_TABLE, |t| {
+        t.add_column("metadata_author", types::text().nullable(true));
+    });
+    m.change_table(TLM_LEVEL_0_TABLE, |t| {
+        t.add_column("metadata_updated_at", types::integer().nullable(true));
+    });
+    m.create_table_if_not_exists(TLM_LEVEL_0_METADATA_HISTORY_TABLE, |t| {
+        t.add_column(
+            "id",
+            types::integer()
+                .primary(true)
+                .increments(true)
+                .nullable(false),
+        );
+        t.add_column("uuid", types::text().nullable(false));
+        t.add_column("revision", types::integer().nullable(false));
+        t.add_column("metadata", types::text().nullable(false));
+        t.add_column("author", types::text().nullable(true));
+        t.add_column("createdate", types::integer().nullable(false));
+    });
+    m.inject_custom(format!(
+        "CREATE UNIQUE INDEX IF NOT EXISTS level_0_metadata_history_uuid_revision ON {} (uuid, revision)",
+        TLM_LEVEL_0_METADATA_HISTORY_TABLE,
+    ));
+}
+
+/// Creates `tag`, one row per tag name, and `level_0_tag`, which links
+/// packets to their tags.
+fn create_tag_tables(m: &mut Migration) {
+    m.create_table_if_not_exists(TLM_TAG_TABLE, |t| {
+        t.add_column(
+            "id",
+            types::integer()
+                .primary(true)
+                .increments(true)
+                .nullable(false),
+        );
         t.add_column("name", types::text().nullable(false).unique(true));
+        t.add_column("createdate", types::integer().nullable(false));
+    });
+    m.create_table_if_not_exists(TLM_LEVEL_0_TAG_TABLE, |t| {
+        t.add_column("level_0_id", types::integer().nullable(false));
+        t.add_column("tag_id", types::integer().nullable(false));
+        t.add_column("createdate", types::integer().nullable(false));
+    });
+    m.inject_custom(format!(
+        "CREATE UNIQUE INDEX IF NOT EXISTS level_0_tag_level_0_id_tag_id ON {table} (level_0_id, tag_id);\
+         CREATE INDEX IF NOT EXISTS level_0_tag_tag_id ON {table} (tag_id)",
+        table = TLM_LEVEL_0_TAG_TABLE,
+    ));
+}
+
+/// Gives each `single_value` a type and the time it was last written, and
+/// creates `single_value_history`, which keeps every write.
+///
+/// Values stored before this migration are strings, written at an unknown time.
+fn add_single_value_types(m: &mut Migration) {
+    m.change_table(TLM_SINGLE_VALUE_TABLE, |t| {
+        t.add_column("type", types::text().nullable(false).default("string"));
+    });
+    m.change_table(TLM_SINGLE_VALUE_TABLE, |t| {
+        t.add_column("updated_at", types::integer().nullable(true));
+    });
+    m.create_table_if_not_exists(TLM_SINGLE_VALUE_HISTORY_TABLE, |t| {
+        t.add_column(
+            "id",
+            types::integer()
+                .primary(true)
+                .increments(true)
+                .nullable(false),
+        );
+        t.add_column("name", types::text().nullable(false));
+        t.add_column("type", types::text().nullable(false));
         t.add_column("value", types::text().nullable(false));
+        t.add_column("createdate", types::integer().nullable(false));
     });
-}
\ No newline at end of file
+    m.inject_custom(format!(
+        "CREATE INDEX IF NOT EXISTS single_value_history_name_createdate ON {} (name, createdate)",
+        TLM_SINGLE_VALUE_HISTORY_TABLE,
+    ));
+}
+
+/// Numbers the writes of each `single_value`, so a write can be made
+/// conditional on the revision it replaces. Writes already in the history
+/// are numbered in order, and values without any history are at revision 1.
+fn add_single_value_revision(m: &mut Migration) {
+    m.change_table(TLM_SINGLE_VALUE_TABLE, |t| {
+        t.add_column("revision", types::integer().nullable(false).default(1));
+    });
+    m.change_table(TLM_SINGLE_VALUE_HISTORY_TABLE, |t| {
+        t.add_column("revision", types::integer().nullable(false).default(0));
+    });
+    m.inject_custom(format!(
+        "UPDATE {history} SET revision = (\
+             SELECT count(*) FROM {history} AS earlier \
+             WHERE earlier.name = {history}.name AND earlier.id <= {history}.id\
+         );\
+         UPDATE {table} SET revision = max(1, coalesce(\
+             (SELECT max(revision) FROM {history} WHERE {history}.name = {table}.name), 0\
+         ))",
+        table = TLM_SINGLE_VALUE_TABLE,
+        history = TLM_SINGLE_VALUE_HISTORY_TABLE,
+    ));
+}
+
+/// Groups `single_value`s into namespaces and lets each expire.
+///
+/// Names go from unique to unique per namespace, which SQLite can only do by
+/// rebuilding the table. Values stored before this migration, and their
+/// history, end up in the default namespace, `""`, and never expire.
+fn add_single_value_namespaces(m: &mut Migration) {
+    let rebuilt = format!("{}_namespaced", TLM_SINGLE_VALUE_TABLE);
+    m.create_table(rebuilt.as_str(), |t| {
+        t.add_column("namespace", types::text().nullable(false).default(""));
+        t.add_column("name", types::text().nullable(false));
+        t.add_column("value", types::text().nullable(false));
+        t.add_column("type", types::text().nullable(false).default("string"));
+        t.add_column("updated_at", types::integer().nullable(true));
+        t.add_column("revision", types::integer().nullable(false).default(1));
+        t.add_column("expires_at", types::integer().nullable(true));
+    });
+    m.inject_custom(format!(
+        "INSERT INTO {rebuilt} (name, value, type, updated_at, revision) \
+             SELECT name, value, type, updated_at, revision FROM {table};\
+         DROP TABLE {table};\
+         ALTER TABLE {rebuilt} RENAME TO {table};\
+         CREATE UNIQUE INDEX IF NOT EXISTS single_value_namespace_name ON {table} (namespace, name);\
+         CREATE INDEX IF NOT EXISTS single_value_expires_at ON {table} (expires_at)",
+        table = TLM_SINGLE_VALUE_TABLE,
+        rebuilt = rebuilt,
+    ));
+
+    m.change_table(TLM_SINGLE_VALUE_HISTORY_TABLE, |t| {
+        t.add_column("namespace", types::text().nullable(false).default(""));
+    });
+    m.inject_custom(format!(
+        "DROP INDEX IF EXISTS single_value_history_name_createdate;\
+         CREATE INDEX IF NOT EXISTS single_value_history_namespace_name_createdate \
+             ON {} (namespace, name, createdate)",
+        TLM_SINGLE_VALUE_HISTORY_TABLE,
+    ));
+}
+
+/// Scopes each idempotency key to the endpoint it was sent to, so the same
+/// key on `/packets` and `/packets/batch` can't replay the other's reply.
+///
+/// Keys go from unique to unique per endpoint, which SQLite can only do by
+/// rebuilding the table. Keys recorded before this migration don't say which
+/// endpoint they were for, so they are forgotten; at worst a retry in flight
+/// is stored again, and deduplicated by its sha256.
+fn scope_idempotency_keys(m: &mut Migration) {
+    m.drop_table_if_exists(TLM_IDEMPOTENCY_KEY_TABLE);
+    m.create_table(TLM_IDEMPOTENCY_KEY_TABLE, |t| {
+        t.add_column("endpoint", types::text().nullable(false));
+        t.add_column("key", types::text().nullable(false));
+        t.add_column("createdate", types::integer().nullable(false));
+        t.add_column("status", types::integer().nullable(true));
+        t.add_column("content_type", types::text().nullable(true));
+        t.add_column("body", types::text().nullable(true));
+    });
+    m.inject_custom(format!(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idempotency_key_endpoint_key ON {table} (endpoint, key);\
+         CREATE INDEX IF NOT EXISTS idempotency_key_createdate ON {table} (createdate)",
+        table = TLM_IDEMPOTENCY_KEY_TABLE,
+    ));
+}
@@ -0,0 +1,220 @@
+use rusqlite::{params, Result, Row, Connection};
+use serde::{Serialize, Deserialize};
+
+use super::context::{TLM_LEVEL_0_TABLE, TLM_RETENTION_LOG_TABLE};
+use super::{metadata_history, tag};
+
+/// How long packets of one `filetype` are kept. A packet is purged once it
+/// breaks any of the limits set; unset limits don't apply.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RetentionRule {
+    pub filetype: String,
+    /// Purge packets received longer ago than this, in seconds.
+    pub max_age_secs: Option<u64>,
+    /// Purge the oldest packets once all of this filetype's packets
+    /// together take up more than this many bytes.
+    pub max_total_bytes: Option<u64>,
+    /// Purge all but this many of the newest packets.
+    pub keep_last: Option<u64>,
+}
+
+/// A packet due to be purged, and why.
+#[derive(Debug, PartialEq)]
+pub struct Expired {
+    pub id: i64,
+    pub uuid: String,
+    pub filetype: Option<String>,
+    pub size: i64,
+    /// `max_age`, `keep_last` or `max_total_bytes`, or `trash`
+    pub reason: String,
+}
+
+/// Finds up to `limit` packets that break `rule` at time `now`, oldest first.
+/// Packets already in the trash don't count. Packets under legal hold count
+/// towards the limits, but are never found.
+pub fn expired(conn: &Connection, rule: &RetentionRule, now: i64, limit: u32) -> Result<Vec<Expired>> {
+    let received_before = rule.max_age_secs.map(|secs| now - secs as i64 * 1000);
+
+    // Rank and running size are counted from the newest packet back, so the
+    // oldest packets are the ones past the limits.
+    let sql = format!(
+        "select id, uuid, filetype, size, reason from (
+            select id, uuid, filetype, size, held,
+                case
+                    when ?2 is not null and received_at < ?2 then 'max_age'
+                    when ?3 is not null and newer > ?3 then 'keep_last'
+                    when ?4 is not null and running_bytes > ?4 then 'max_total_bytes'
+                end as reason
+            from (
+                select id, uuid, filetype, received_at, held, length(packet) as size,
+                    row_number() over newest_first as newer,
+                    sum(length(packet)) over newest_first as running_bytes
+                from {} where filetype = ?1 and deleted_at is null
+                window newest_first as (order by id desc)
+            )
+        )
+        where reason is not null and not held
+        order by id limit ?5",
+        TLM_LEVEL_0_TABLE,
+    );
+
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(
+        params![
+            rule.filetype,
+            received_before,
+            rule.keep_last.map(|n| n as i64),
+            rule.max_total_bytes.map(|n| n as i64),
+            limit,
+        ],
+        expired_from_row,
+    )?;
+
+    rows.collect()
+}
+
+/// Finds up to `limit` packets that went in the trash before `deleted_before`,
+/// oldest first. Packets can't be put under legal hold while in the trash,
+/// so none of these are held.
+pub fn trashed(conn: &Connection, deleted_before: i64, limit: u32) -> Result<Vec<Expired>> {
+    let sql = format!(
+        "select id, uuid, filetype, length(packet), 'trash' from {}
+        where deleted_at < ?1
+        order by id limit ?2",
+        TLM_LEVEL_0_TABLE,
+    );
+
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params![deleted_before, limit], expired_from_row)?;
+
+    rows.collect()
+}
+
+/// Deletes `expired` packets, with their metadata history and tags, for good
+/// and records each one in the `retention_log`, purged at `now`.
+pub fn purge(conn: &Connection, expired: &[Expired], now: i64) -> Result<()> {
+    let mut delete = conn.prepare(&format!("delete from {} where id = ?1", TLM_LEVEL_0_TABLE))?;
+    let mut log = conn.prepare(&format!(
+        "insert into {} (purged_at, uuid, filetype, size, reason) values (?1, ?2, ?3, ?4, ?5)",
+        TLM_RETENTION_LOG_TABLE,
+    ))?;
+
+    for packet in expired {
+        delete.execute(params![packet.id])?;
+        metadata_history::forget(conn, &packet.uuid)?;
+        tag::forget(conn, packet.id)?;
+        log.execute(params![now, packet.uuid, packet.filetype, packet.size, packet.reason])?;
+    }
+
+    Ok(())
+}
+
+/// Switches the db to incremental auto-vacuum, unless it is already. Returns
+/// whether it had to.
+///
+/// This has to rewrite the whole file with a `VACUUM`, so it is done once, at
+/// startup, and can't run inside a transaction.
+pub fn enable_incremental_vacuum(conn: &Connection) -> Result<bool> {
+    let auto_vacuum: i64 = conn.pragma_query_value(None, "auto_vacuum", |row| row.get(0))?;
+    // 2 is INCREMENTAL
+    if auto_vacuum == 2 {
+        return Ok(false);
+    }
+
+    conn.execute_batch("PRAGMA auto_vacuum = INCREMENTAL; VACUUM;")?;
+    Ok(true)
+}
+
+/// Hands the pages freed by purging back to the filesystem.
+pub fn incremental_vacuum(conn: &Connection) -> Result<()> {
+    conn.execute_batch("PRAGMA incremental_vacuum;")
+}
+
+fn expired_from_row(row: &Row) -> Result<Expired> {
+    Ok(Expired {
+        id: row.get(0)?,
+        uuid: row.get(1)?,
+        filetype: row.get(2)?,
+        size: row.get(3)?,
+        reason: row.get(4)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migrations;
+
+    /// Four 10-byte `log` packets received a second apart, and one `image`.
+    fn test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        migrations::apply_all(&conn).unwrap();
+        for (i, filetype) in ["log", "log", "log", "log", "image"].iter().enumerate() {
+            conn.execute(
+                "insert into level_0 (uuid, createdate, received_at, metadata, filetype, packet)
+                values (?1, ?2, ?2, '{}', ?3, ?4)",
+                params![format!("uuid-{}", i), (i as i64 + 1) * 1000, filetype, vec![0u8; 10]],
+            )
+            .unwrap();
+        }
+        conn
+    }
+
+    fn rule() -> RetentionRule {
+        RetentionRule { filetype: "log".into(), max_age_secs: None, max_total_bytes: None, keep_last: None }
+    }
+
+    fn uuids(expired: &[Expired]) -> Vec<&str> {
+        expired.iter().map(|packet| packet.uuid.as_str()).collect()
+    }
+
+    #[test]
+    fn it_finds_packets_past_each_limit() {
+        let conn = test_db();
+
+        let old = expired(&conn, &RetentionRule { max_age_secs: Some(2), ..rule() }, 4000, 10).unwrap();
+        assert_eq!(uuids(&old), ["uuid-0"]);
+        assert_eq!(old[0].reason, "max_age");
+
+        let surplus = expired(&conn, &RetentionRule { keep_last: Some(1), ..rule() }, 4000, 10).unwrap();
+        assert_eq!(uuids(&surplus), ["uuid-0", "uuid-1", "uuid-2"]);
+
+        let oversize = expired(&conn, &RetentionRule { max_total_bytes: Some(25), ..rule() }, 4000, 10).unwrap();
+        assert_eq!(uuids(&oversize), ["uuid-0", "uuid-1"]);
+        assert_eq!(oversize[0].reason, "max_total_bytes");
+
+        assert!(expired(&conn, &rule(), 4000, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn it_purges_in_batches_and_logs_them() {
+        let conn = test_db();
+        let rule = RetentionRule { keep_last: Some(1), ..rule() };
+
+        let batch = expired(&conn, &rule, 4000, 2).unwrap();
+        assert_eq!(batch.len(), 2);
+        purge(&conn, &batch, 5000).unwrap();
+
+        let rest = expired(&conn, &rule, 4000, 2).unwrap();
+        assert_eq!(uuids(&rest), ["uuid-2"]);
+
+        let logged: i64 = conn
+            .query_row("select count(*) from retention_log where reason = 'keep_last' and purged_at = 5000", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(logged, 2);
+    }
+
+    #[test]
+    fn it_leaves_the_trash_to_its_grace_period() {
+        let conn = test_db();
+        conn.execute("update level_0 set deleted_at = 1000 * id where uuid in ('uuid-0', 'uuid-3')", []).unwrap();
+
+        let surplus = expired(&conn, &RetentionRule { keep_last: Some(1), ..rule() }, 4000, 10).unwrap();
+        assert_eq!(uuids(&surplus), ["uuid-1"]);
+
+        let trash = trashed(&conn, 2000, 10).unwrap();
+        assert_eq!(uuids(&trash), ["uuid-0"]);
+        assert_eq!(trash[0].filetype.as_deref(), Some("log"));
+        assert_eq!(trashed(&conn, 5000, 10).unwrap().len(), 2);
+    }
+}
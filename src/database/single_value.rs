@@ -1,22 +1,518 @@
-use rusqlite::{params, Result, Connection};
+use rusqlite::{params, OptionalExtension, Params, Result, Row, Connection};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::ser::{SerializeStruct, Serializer};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
 
-pub fn get(conn: &Connection, key: &str) -> Result<Option<String>> {
-    match conn.query_row(
-        "select value from singlevalue where key = ?1",
-        params![key],
-        |row| Ok(row.get(0)?),
-    ) {
-        Ok(v) => Ok(Some(v)),
-        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(x) => Err(x),
+use crate::timestamp::Timestamp;
+
+use super::context::{TLM_SINGLE_VALUE_TABLE, TLM_SINGLE_VALUE_HISTORY_TABLE};
+
+/// Default number of writes returned by `history`.
+pub const DEFAULT_HISTORY_LIMIT: u32 = 1000;
+/// Upper bound on the number of writes returned by `history`.
+pub const MAX_HISTORY_LIMIT: u32 = 10000;
+
+/// The type of a value, which it is checked against whenever it is written.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueType {
+    Int,
+    Float,
+    Bool,
+    #[default]
+    String,
+    /// Any JSON.
+    Json,
+    /// Binary data, as base64 in JSON.
+    Bytes,
+    /// Only in the history: the value was deleted, or expired, here. Values
+    /// can't be stored as this type.
+    Deleted,
+}
+
+impl ValueType {
+    fn as_str(self) -> &'static str {
+        match self {
+            ValueType::Int => "int",
+            ValueType::Float => "float",
+            ValueType::Bool => "bool",
+            ValueType::String => "string",
+            ValueType::Json => "json",
+            ValueType::Bytes => "bytes",
+            ValueType::Deleted => "deleted",
+        }
+    }
+}
+
+impl ToSql for ValueType {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for ValueType {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "int" => Ok(ValueType::Int),
+            "float" => Ok(ValueType::Float),
+            "bool" => Ok(ValueType::Bool),
+            "string" => Ok(ValueType::String),
+            "json" => Ok(ValueType::Json),
+            "bytes" => Ok(ValueType::Bytes),
+            "deleted" => Ok(ValueType::Deleted),
+            other => Err(FromSqlError::Other(format!("unknown value type {:?}", other).into())),
+        }
+    }
+}
+
+/// A value that has been checked against its type.
+///
+/// It is kept as the text it is stored as, and shows up in JSON as
+/// `{"type": "float", "value": 7.9}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypedValue {
+    value_type: ValueType,
+    text: String,
+}
+
+impl TypedValue {
+    /// Checks that `value` is a `value_type`: a JSON integer for `int`, any
+    /// number for `float`, a base64 string for `bytes` and so on.
+    pub fn new(value_type: ValueType, value: &Value) -> Result<TypedValue, String> {
+        let text = match (value_type, value) {
+            (ValueType::Int, Value::Number(number)) if number.is_i64() => number.to_string(),
+            (ValueType::Float, Value::Number(number)) => number.as_f64().unwrap_or_default().to_string(),
+            (ValueType::Bool, Value::Bool(boolean)) => boolean.to_string(),
+            (ValueType::String, Value::String(string)) => string.clone(),
+            (ValueType::Json, value) => value.to_string(),
+            (ValueType::Bytes, Value::String(encoded)) => match base64::decode(encoded) {
+                Ok(bytes) => base64::encode(bytes),
+                Err(error) => return Err(format!("bytes values must be base64: {}", error)),
+            },
+            (ValueType::Deleted, _) => return Err("values can't be stored as deleted".to_string()),
+            (value_type, value) => return Err(format!("{} is not a valid {} value", value, value_type.as_str())),
+        };
+        Ok(TypedValue { value_type, text })
+    }
+
+    /// The value as JSON.
+    pub fn to_json(&self) -> Value {
+        let value = match self.value_type {
+            ValueType::Int => self.text.parse::<i64>().ok().map(Value::from),
+            ValueType::Float => self.text.parse::<f64>().ok().map(Value::from),
+            ValueType::Bool => self.text.parse::<bool>().ok().map(Value::from),
+            ValueType::Json => serde_json::from_str(&self.text).ok(),
+            ValueType::Deleted => Some(Value::Null),
+            ValueType::String | ValueType::Bytes => None,
+        };
+        value.unwrap_or_else(|| Value::String(self.text.clone()))
+    }
+
+    /// The value, if it is an `int`.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self.value_type {
+            ValueType::Int => self.text.parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+impl Serialize for TypedValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("TypedValue", 2)?;
+        state.serialize_field("type", &self.value_type)?;
+        state.serialize_field("value", &self.to_json())?;
+        state.end()
+    }
+}
+
+/// A named value in the `single_value` store.
+///
+/// Names are unique within a namespace, e.g. one per spacecraft or ground
+/// station. The default namespace is `""`.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct SingleValue {
+    pub namespace: String,
+    pub name: String,
+    #[serde(flatten)]
+    pub value: TypedValue,
+    /// When the value was last written; unknown for values written before
+    /// writes were timed.
+    pub updated_at: Option<Timestamp>,
+    /// Counts the writes of the value, including any before it was last
+    /// deleted, so it never repeats.
+    pub revision: i64,
+    /// When the value expires, if it does. From then on it reads as missing,
+    /// until it is swept away.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<Timestamp>,
+}
+
+/// One write of a value.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Write {
+    #[serde(flatten)]
+    pub value: TypedValue,
+    pub at: Timestamp,
+    /// The revision the write made.
+    pub revision: i64,
+}
+
+/// A write of one of the values being watched.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Change {
+    /// Orders the writes of every value. Watch from the last one seen to get
+    /// the writes after it.
+    pub seq: i64,
+    pub name: String,
+    #[serde(flatten)]
+    pub write: Write,
+}
+
+/// Which writes of a value to return.
+#[derive(Deserialize, Debug, Default)]
+pub struct HistoryQuery {
+    /// Only writes at or after this time.
+    pub from: Option<Timestamp>,
+    /// Only writes before this time.
+    pub to: Option<Timestamp>,
+    pub limit: Option<u32>,
+}
+
+impl HistoryQuery {
+    /// The number of writes to return, defaulted and clamped to `MAX_HISTORY_LIMIT`.
+    pub fn limit(&self) -> u32 {
+        self.limit.unwrap_or(DEFAULT_HISTORY_LIMIT).min(MAX_HISTORY_LIMIT)
     }
 }
 
-pub fn set(conn: &Connection, key: &str, value: &str) -> Result<()> {
+/// Looks up the value stored under `name` in `namespace`, unless there's
+/// none or it had expired by `now`.
+pub fn get(conn: &Connection, namespace: &str, name: &str, now: i64) -> Result<Option<SingleValue>> {
+    let sql = format!(
+        "select {} from {} where namespace = ?1 and name = ?2 and {}",
+        VALUE_COLUMNS, TLM_SINGLE_VALUE_TABLE, UNEXPIRED_AT_3,
+    );
+    conn.query_row(&sql, params![namespace, name, now], value_from_row).optional()
+}
+
+/// Stores `value` under `name` in `namespace` at `now`, to expire at
+/// `expires_at` if given, replacing whatever was there. The write is appended
+/// to the value's history. Returns the value's new revision.
+///
+/// The caller holds a write transaction, which is also what makes checking the
+/// current revision before a `set` a compare-and-swap.
+pub fn set(
+    conn: &Connection,
+    namespace: &str,
+    name: &str,
+    value: &TypedValue,
+    expires_at: Option<i64>,
+    now: i64,
+) -> Result<i64> {
+    // An expired value that hasn't been swept yet goes the same way as if it had.
+    record_deletions(
+        conn,
+        "expires_at",
+        "namespace = ?1 and name = ?2 and expires_at <= ?3",
+        params![namespace, name, now],
+    )?;
+    // A value that was deleted carries on from the last revision in its history.
+    conn.execute(
+        &format!(
+            "insert into {table} (namespace, name, type, value, updated_at, revision, expires_at)
+            values (?1, ?2, ?3, ?4, ?5,
+                coalesce((select max(revision) from {history} where namespace = ?1 and name = ?2), 0) + 1, ?6)
+            on conflict (namespace, name) do update set type = excluded.type, value = excluded.value,
+                updated_at = excluded.updated_at, revision = max({table}.revision + 1, excluded.revision),
+                expires_at = excluded.expires_at",
+            table = TLM_SINGLE_VALUE_TABLE,
+            history = TLM_SINGLE_VALUE_HISTORY_TABLE,
+        ),
+        params![namespace, name, value.value_type, value.text, now, expires_at],
+    )?;
+    let revision: i64 = conn.query_row(
+        &format!("select revision from {} where namespace = ?1 and name = ?2", TLM_SINGLE_VALUE_TABLE),
+        params![namespace, name],
+        |row| row.get(0),
+    )?;
     conn.execute(
-        "insert into single_value (key, value) values (?1, ?2)
-        on conflict (key) do update set value = ?2 where key = ?1",
-        params![key, value],
+        &format!(
+            "insert into {} (namespace, name, type, value, createdate, revision) values (?1, ?2, ?3, ?4, ?5, ?6)",
+            TLM_SINGLE_VALUE_HISTORY_TABLE,
+        ),
+        params![namespace, name, value.value_type, value.text, now, revision],
     )?;
-    Ok(())
+    Ok(revision)
+}
+
+/// Drops the value stored under `name` in `namespace`, returning whether
+/// there was one that hadn't expired by `now`. Its history is kept, and gains
+/// a `deleted` write at `now`.
+///
+/// The caller holds a write transaction.
+pub fn delete(conn: &Connection, namespace: &str, name: &str, now: i64) -> Result<bool> {
+    let filter = format!("namespace = ?1 and name = ?2 and {}", UNEXPIRED_AT_3);
+    record_deletions(conn, "?3", &filter, params![namespace, name, now])?;
+    let sql = format!("delete from {} where {}", TLM_SINGLE_VALUE_TABLE, filter);
+    let deleted = conn.execute(&sql, params![namespace, name, now])?;
+    Ok(deleted > 0)
+}
+
+/// Lists the values in `namespace` whose names start with `prefix` (every
+/// value for an empty one) and that hadn't expired by `now`, by name.
+pub fn list(conn: &Connection, namespace: &str, prefix: &str, now: i64) -> Result<Vec<SingleValue>> {
+    // GLOB, unlike LIKE, is case-sensitive, so it can use the index on `name`.
+    let sql = format!(
+        "select {} from {} where namespace = ?1 and name glob ?2 and {} order by name",
+        VALUE_COLUMNS, TLM_SINGLE_VALUE_TABLE, UNEXPIRED_AT_3,
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params![namespace, glob_prefix(prefix), now], value_from_row)?;
+
+    rows.collect()
+}
+
+/// Deletes every value that had expired by `now`, returning how many. Each
+/// one's history gains a `deleted` write at the time it expired.
+///
+/// The caller holds a write transaction.
+pub fn sweep(conn: &Connection, now: i64) -> Result<usize> {
+    record_deletions(conn, "expires_at", "expires_at <= ?1", params![now])?;
+    let sql = format!("delete from {} where expires_at <= ?1", TLM_SINGLE_VALUE_TABLE);
+    conn.execute(&sql, params![now])
+}
+
+/// Appends a `deleted` write, made at `at`, to the history of every value
+/// matching `filter`, just before they are deleted or replaced.
+fn record_deletions<P: Params>(conn: &Connection, at: &str, filter: &str, params: P) -> Result<usize> {
+    let sql = format!(
+        "insert into {history} (namespace, name, type, value, createdate, revision)
+        select namespace, name, 'deleted', '', {at}, revision + 1 from {table} where {filter}",
+        history = TLM_SINGLE_VALUE_HISTORY_TABLE,
+        table = TLM_SINGLE_VALUE_TABLE,
+        at = at,
+        filter = filter,
+    );
+    conn.execute(&sql, params)
+}
+
+/// The writes of the value under `name` in `namespace` that match `query`,
+/// oldest first.
+pub fn history(conn: &Connection, namespace: &str, name: &str, query: &HistoryQuery) -> Result<Vec<Write>> {
+    let sql = format!(
+        "select type, value, createdate, revision from {}
+        where namespace = ?1 and name = ?2 and createdate >= ?3 and createdate < ?4
+        order by createdate, id limit ?5",
+        TLM_SINGLE_VALUE_HISTORY_TABLE,
+    );
+    let from = query.from.map_or(i64::MIN, |from| from.0);
+    let to = query.to.map_or(i64::MAX, |to| to.0);
+
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params![namespace, name, from, to, query.limit()], |row| {
+        Ok(Write {
+            value: TypedValue { value_type: row.get(0)?, text: row.get(1)? },
+            at: row.get(2)?,
+            revision: row.get(3)?,
+        })
+    })?;
+
+    rows.collect()
+}
+
+/// The `seq` of the latest write of any value, or 0 if there's been none.
+pub fn latest_seq(conn: &Connection) -> Result<i64> {
+    let sql = format!("select coalesce(max(id), 0) from {}", TLM_SINGLE_VALUE_HISTORY_TABLE);
+    conn.query_row(&sql, [], |row| row.get(0))
+}
+
+/// Up to `limit` writes after `since` of the values in `namespace` whose
+/// names start with `prefix`, oldest first.
+pub fn changes(conn: &Connection, namespace: &str, prefix: &str, since: i64, limit: u32) -> Result<Vec<Change>> {
+    let sql = format!(
+        "select id, name, type, value, createdate, revision from {}
+        where id > ?1 and namespace = ?2 and name glob ?3
+        order by id limit ?4",
+        TLM_SINGLE_VALUE_HISTORY_TABLE,
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params![since, namespace, glob_prefix(prefix), limit], |row| {
+        Ok(Change {
+            seq: row.get(0)?,
+            name: row.get(1)?,
+            write: Write {
+                value: TypedValue { value_type: row.get(2)?, text: row.get(3)? },
+                at: row.get(4)?,
+                revision: row.get(5)?,
+            },
+        })
+    })?;
+
+    rows.collect()
+}
+
+/// A GLOB pattern matching everything that starts with `prefix`.
+fn glob_prefix(prefix: &str) -> String {
+    let mut pattern = String::with_capacity(prefix.len() + 1);
+    for c in prefix.chars() {
+        match c {
+            '*' | '?' | '[' => {
+                pattern.push('[');
+                pattern.push(c);
+                pattern.push(']');
+            }
+            c => pattern.push(c),
+        }
+    }
+    pattern.push('*');
+    pattern
+}
+
+/// Columns selected to build a `SingleValue`, in `value_from_row` order.
+const VALUE_COLUMNS: &str = "namespace, name, type, value, updated_at, revision, expires_at";
+
+/// Leaves out values that had expired by the time bound as `?3`.
+const UNEXPIRED_AT_3: &str = "(expires_at is null or expires_at > ?3)";
+
+fn value_from_row(row: &Row) -> Result<SingleValue> {
+    Ok(SingleValue {
+        namespace: row.get(0)?,
+        name: row.get(1)?,
+        value: TypedValue { value_type: row.get(2)?, text: row.get(3)? },
+        updated_at: row.get(4)?,
+        revision: row.get(5)?,
+        expires_at: row.get(6)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::database::migrations;
+
+    fn typed(value_type: ValueType, value: Value) -> TypedValue {
+        TypedValue::new(value_type, &value).unwrap()
+    }
+
+    #[test]
+    fn it_sets_lists_and_deletes_values() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrations::apply_all(&conn).unwrap();
+
+        set(&conn, "", "sc1.mode", &typed(ValueType::String, json!("safe")), None, 1000).unwrap();
+        set(&conn, "", "sc1.mode", &typed(ValueType::String, json!("nominal")), None, 2000).unwrap();
+        set(&conn, "", "sc1.battery", &typed(ValueType::Float, json!(7.9)), None, 1000).unwrap();
+        set(&conn, "", "sc1*", &typed(ValueType::Bool, json!(true)), None, 1000).unwrap();
+        set(&conn, "", "sc2.mode", &typed(ValueType::String, json!("safe")), None, 1000).unwrap();
+
+        let mode = get(&conn, "", "sc1.mode", 0).unwrap().unwrap();
+        assert_eq!(mode.value.to_json(), json!("nominal"));
+        assert_eq!(mode.updated_at, Some(Timestamp(2000)));
+        assert!(get(&conn, "", "sc3.mode", 0).unwrap().is_none());
+
+        let names = |prefix: &str| list(&conn, "", prefix, 0).unwrap().into_iter().map(|value| value.name).collect::<Vec<_>>();
+        assert_eq!(names("sc1."), ["sc1.battery", "sc1.mode"]);
+        assert_eq!(names("sc1*"), ["sc1*"]);
+        assert_eq!(names("").len(), 4);
+
+        assert!(delete(&conn, "", "sc1.mode", 0).unwrap());
+        assert!(!delete(&conn, "", "sc1.mode", 0).unwrap());
+        assert!(get(&conn, "", "sc1.mode", 0).unwrap().is_none());
+    }
+
+    #[test]
+    fn it_never_repeats_a_revision() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrations::apply_all(&conn).unwrap();
+
+        let mode = typed(ValueType::String, json!("safe"));
+        assert_eq!(set(&conn, "", "sc1.mode", &mode, None, 1000).unwrap(), 1);
+        assert_eq!(set(&conn, "", "sc1.mode", &mode, None, 2000).unwrap(), 2);
+        assert!(delete(&conn, "", "sc1.mode", 2500).unwrap());
+        assert_eq!(set(&conn, "", "sc1.mode", &mode, None, 3000).unwrap(), 4);
+        assert_eq!(get(&conn, "", "sc1.mode", 0).unwrap().unwrap().revision, 4);
+
+        let writes = history(&conn, "", "sc1.mode", &HistoryQuery::default()).unwrap();
+        assert_eq!(writes.iter().map(|write| write.revision).collect::<Vec<_>>(), [1, 2, 3, 4]);
+        assert_eq!((writes[2].value.value_type, writes[2].at), (ValueType::Deleted, Timestamp(2500)));
+        assert_eq!(writes[2].value.to_json(), Value::Null);
+        assert_eq!(typed(ValueType::Int, json!(-4)).as_i64(), Some(-4));
+        assert_eq!(mode.as_i64(), None);
+    }
+
+    #[test]
+    fn it_lists_changes_after_a_seq() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrations::apply_all(&conn).unwrap();
+        assert_eq!(latest_seq(&conn).unwrap(), 0);
+
+        let mode = typed(ValueType::String, json!("safe"));
+        for name in &["sc1.mode", "sc2.mode", "sc1.mode"] {
+            set(&conn, "", name, &mode, None, 1000).unwrap();
+        }
+        assert_eq!(latest_seq(&conn).unwrap(), 3);
+
+        let changes = changes(&conn, "", "sc1.", 1, 10).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!((changes[0].seq, changes[0].write.revision), (3, 2));
+    }
+
+    #[test]
+    fn it_checks_types_and_keeps_every_write() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrations::apply_all(&conn).unwrap();
+
+        assert!(TypedValue::new(ValueType::Int, &json!(1.5)).is_err());
+        assert!(TypedValue::new(ValueType::Bool, &json!("true")).is_err());
+        assert!(TypedValue::new(ValueType::Bytes, &json!("not base64!")).is_err());
+        assert!(TypedValue::new(ValueType::Deleted, &Value::Null).is_err());
+        assert_eq!(typed(ValueType::Float, json!(2)).to_json(), json!(2.0));
+        assert_eq!(typed(ValueType::Json, json!({"a": [1]})).to_json(), json!({"a": [1]}));
+        assert_eq!(typed(ValueType::Bytes, json!("AAE=")).to_json(), json!("AAE="));
+
+        for (at, volts) in [(1000, 7.9), (2000, 7.8), (3000, 7.6)] {
+            set(&conn, "", "sc1.battery", &typed(ValueType::Float, json!(volts)), None, at).unwrap();
+        }
+        set(&conn, "", "sc1.battery", &typed(ValueType::Int, json!(8)), None, 4000).unwrap();
+
+        let writes = history(&conn, "", "sc1.battery", &HistoryQuery { from: Some(Timestamp(2000)), to: Some(Timestamp(4000)), limit: None }).unwrap();
+        assert_eq!(writes.iter().map(|write| write.value.to_json()).collect::<Vec<_>>(), [json!(7.8), json!(7.6)]);
+        assert_eq!(writes[0].at, Timestamp(2000));
+        assert_eq!(history(&conn, "", "sc1.battery", &HistoryQuery::default()).unwrap().len(), 4);
+        assert_eq!(get(&conn, "", "sc1.battery", 0).unwrap().unwrap().value.value_type, ValueType::Int);
+    }
+
+    #[test]
+    fn it_keeps_namespaces_apart_and_expires_values() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrations::apply_all(&conn).unwrap();
+
+        let pass = typed(ValueType::Bool, json!(true));
+        assert_eq!(set(&conn, "sc1", "pass", &pass, Some(5000), 1000).unwrap(), 1);
+        assert_eq!(set(&conn, "sc2", "pass", &pass, None, 1000).unwrap(), 1);
+        assert!(get(&conn, "", "pass", 1000).unwrap().is_none());
+
+        let sc1_pass = get(&conn, "sc1", "pass", 4999).unwrap().unwrap();
+        assert_eq!((sc1_pass.namespace.as_str(), sc1_pass.expires_at), ("sc1", Some(Timestamp(5000))));
+        assert!(get(&conn, "sc1", "pass", 5000).unwrap().is_none());
+        assert!(list(&conn, "sc1", "", 5000).unwrap().is_empty());
+        assert!(!delete(&conn, "sc1", "pass", 5000).unwrap());
+        assert_eq!(changes(&conn, "sc2", "", 0, 10).unwrap().len(), 1);
+
+        assert_eq!(sweep(&conn, 6000).unwrap(), 1);
+        assert_eq!(list(&conn, "sc2", "", 6000).unwrap().len(), 1);
+        let swept = changes(&conn, "sc1", "", 1, 10).unwrap();
+        assert_eq!((swept[0].write.value.value_type, swept[0].write.at), (ValueType::Deleted, Timestamp(5000)));
+        // Writing it again carries on from its history.
+        assert_eq!(set(&conn, "sc1", "pass", &pass, Some(7000), 6000).unwrap(), 3);
+
+        // Replacing an expired value that hasn't been swept records it as deleted too.
+        assert_eq!(set(&conn, "sc1", "pass", &pass, None, 8000).unwrap(), 5);
+        let types = history(&conn, "sc1", "pass", &HistoryQuery::default()).unwrap().into_iter().map(|write| write.value.value_type);
+        assert_eq!(types.collect::<Vec<_>>(), [ValueType::Bool, ValueType::Deleted, ValueType::Bool, ValueType::Deleted, ValueType::Bool]);
+    }
 }
@@ -0,0 +1,146 @@
+use rusqlite::{params, OptionalExtension, Result, Connection};
+
+use super::context::{TLM_LEVEL_0_TABLE, TLM_LEVEL_0_TAG_TABLE, TLM_TAG_TABLE};
+
+/// Longest tag name we'll store.
+pub const MAX_TAG_LEN: usize = 64;
+
+/// Whether `name` can be used as a tag: letters, digits and `-_.:`, e.g.
+/// `anomaly` or `pass-2026-10-17`. Keeping commas out is what lets listings
+/// take a comma-separated list of tags.
+pub fn is_tag(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_TAG_LEN
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+}
+
+/// Tags packet `uuid` with each of `tags` it doesn't have yet, returning all
+/// of its tags. `None` if there's no such packet or it's in the trash.
+pub fn add(conn: &Connection, uuid: &str, tags: &[String], now: i64) -> Result<Option<Vec<String>>> {
+    let tx = conn.unchecked_transaction()?;
+    let id = match live_id(&tx, uuid)? {
+        Some(id) => id,
+        None => return Ok(None),
+    };
+
+    let mut name = tx.prepare(&format!(
+        "insert or ignore into {} (name, createdate) values (?1, ?2)",
+        TLM_TAG_TABLE,
+    ))?;
+    let mut link = tx.prepare(&format!(
+        "insert or ignore into {} (level_0_id, tag_id, createdate) select ?1, id, ?3 from {} where name = ?2",
+        TLM_LEVEL_0_TAG_TABLE, TLM_TAG_TABLE,
+    ))?;
+    for tag in tags {
+        name.execute(params![tag, now])?;
+        link.execute(params![id, tag, now])?;
+    }
+    drop((name, link));
+
+    let tags = of(&tx, id)?;
+    tx.commit()?;
+    Ok(Some(tags))
+}
+
+/// Takes each of `tags` off packet `uuid`, returning the tags it has left.
+/// `None` if there's no such packet or it's in the trash.
+pub fn remove(conn: &Connection, uuid: &str, tags: &[String]) -> Result<Option<Vec<String>>> {
+    let tx = conn.unchecked_transaction()?;
+    let id = match live_id(&tx, uuid)? {
+        Some(id) => id,
+        None => return Ok(None),
+    };
+
+    let mut unlink = tx.prepare(&format!(
+        "delete from {} where level_0_id = ?1 and tag_id = (select id from {} where name = ?2)",
+        TLM_LEVEL_0_TAG_TABLE, TLM_TAG_TABLE,
+    ))?;
+    for tag in tags {
+        unlink.execute(params![id, tag])?;
+    }
+    drop(unlink);
+
+    let tags = of(&tx, id)?;
+    tx.commit()?;
+    Ok(Some(tags))
+}
+
+/// The tags on packet `id`, in name order.
+pub fn of(conn: &Connection, id: i64) -> Result<Vec<String>> {
+    let sql = format!(
+        "select {tag}.name from {link} join {tag} on {tag}.id = {link}.tag_id
+        where {link}.level_0_id = ?1 order by {tag}.name",
+        tag = TLM_TAG_TABLE,
+        link = TLM_LEVEL_0_TAG_TABLE,
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params![id], |row| row.get(0))?;
+    rows.collect()
+}
+
+/// A subquery of the names of the tags on the packet whose id is in
+/// `id_column`, in name order, as a JSON array.
+pub fn names_json(id_column: &str) -> String {
+    format!(
+        "(select json_group_array(name) from (
+            select {tag}.name from {link} join {tag} on {tag}.id = {link}.tag_id
+            where {link}.level_0_id = {id} order by {tag}.name
+        ))",
+        tag = TLM_TAG_TABLE,
+        link = TLM_LEVEL_0_TAG_TABLE,
+        id = id_column,
+    )
+}
+
+/// A subquery of the ids of packets tagged with `count` tags, bound as that
+/// many parameters after it: any one of them, or every one when `all`.
+pub fn tagged_ids(count: usize, all: bool) -> String {
+    let names = vec!["?"; count].join(", ");
+    let having = if all { format!(" group by {}.level_0_id having count(*) = {}", TLM_LEVEL_0_TAG_TABLE, count) } else { String::new() };
+    format!(
+        "select {link}.level_0_id from {link} join {tag} on {tag}.id = {link}.tag_id where {tag}.name in ({names}){having}",
+        link = TLM_LEVEL_0_TAG_TABLE,
+        tag = TLM_TAG_TABLE,
+        names = names,
+        having = having,
+    )
+}
+
+/// Drops packet `id`'s tags, once the packet itself is gone.
+pub fn forget(conn: &Connection, id: i64) -> Result<()> {
+    let sql = format!("delete from {} where level_0_id = ?1", TLM_LEVEL_0_TAG_TABLE);
+    conn.execute(&sql, params![id])?;
+    Ok(())
+}
+
+fn live_id(conn: &Connection, uuid: &str) -> Result<Option<i64>> {
+    let sql = format!("select id from {} where uuid = ?1 and deleted_at is null", TLM_LEVEL_0_TABLE);
+    conn.query_row(&sql, params![uuid], |row| row.get(0)).optional()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migrations;
+
+    #[test]
+    fn it_adds_and_removes_tags() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrations::apply_all(&conn).unwrap();
+        conn.execute(
+            "insert into level_0 (uuid, createdate, received_at, metadata, packet) values ('uuid-0', 1000, 1000, '{}', x'00')",
+            [],
+        )
+        .unwrap();
+
+        let tags = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+        assert_eq!(add(&conn, "uuid-0", &tags(&["calibration", "anomaly"]), 2000).unwrap(), Some(tags(&["anomaly", "calibration"])));
+        assert_eq!(add(&conn, "uuid-0", &tags(&["anomaly"]), 3000).unwrap(), Some(tags(&["anomaly", "calibration"])));
+        assert_eq!(remove(&conn, "uuid-0", &tags(&["anomaly", "unknown"])).unwrap(), Some(tags(&["calibration"])));
+        assert_eq!(add(&conn, "uuid-9", &tags(&["anomaly"]), 2000).unwrap(), None);
+
+        assert!(is_tag("pass-2026-10-17"));
+        assert!(!is_tag("a,b"));
+        assert!(!is_tag(""));
+    }
+}
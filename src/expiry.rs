@@ -0,0 +1,84 @@
+use std::error::Error;
+
+use crate::database;
+use crate::database::single_value;
+
+use super::config::Config;
+
+use log::{error, info};
+use rusqlite::TransactionBehavior;
+
+/// Keeps the background sweep of expired values running; it stops when this
+/// is dropped.
+pub struct SweepTask {
+    _timer: timer::Timer,
+    _guard: timer::Guard,
+}
+
+/// Starts sweeping expired single values out of the db every
+/// `value_sweep_interval_secs` on a timer thread of its own. An interval of 0
+/// starts nothing.
+pub fn start(config: Config) -> Option<SweepTask> {
+    if config.value_sweep_interval_secs == 0 {
+        return None;
+    }
+
+    let timer = timer::Timer::new();
+    let interval = chrono::Duration::seconds(config.value_sweep_interval_secs as i64);
+    let guard = timer.schedule_repeating(interval, move || {
+        if let Err(error) = sweep(&config) {
+            error!("failed to sweep expired values: {:?}", error);
+        }
+    });
+
+    Some(SweepTask { _timer: timer, _guard: guard })
+}
+
+/// Deletes every single value that has expired, returning how many. Their
+/// history is kept, and watchers see them go.
+pub fn sweep(config: &Config) -> Result<usize, Box<dyn Error>> {
+    let mut conn = database::connection::open(config.db.as_path())?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let swept = single_value::sweep(&tx, config.clock.now()?)?;
+    tx.commit()?;
+
+    if swept > 0 {
+        config.value_changes.notify();
+        info!("swept {} expired values", swept);
+    }
+
+    Ok(swept)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::database::single_value::{TypedValue, ValueType};
+    use crate::testing::TestConfig;
+    use crate::timestamp::Timestamp;
+
+    #[test]
+    fn it_sweeps_expired_values_and_announces_them() {
+        let test = TestConfig::new();
+        let conn = database::connection::open(test.config.db.as_path()).unwrap();
+        let pass = TypedValue::new(ValueType::Bool, &json!(true)).unwrap();
+        let expires_at = TestConfig::START + 1000;
+        single_value::set(&conn, "sc1", "pass", &pass, Some(expires_at), TestConfig::START).unwrap();
+        single_value::set(&conn, "sc1", "mode", &pass, None, TestConfig::START).unwrap();
+
+        let generation = test.config.value_changes.generation();
+        assert_eq!(sweep(&test.config).unwrap(), 0);
+        assert_eq!(test.config.value_changes.generation(), generation);
+
+        test.clock.advance(5000);
+        assert_eq!(sweep(&test.config).unwrap(), 1);
+        assert_eq!(test.config.value_changes.generation(), generation + 1);
+
+        let changes = single_value::changes(&conn, "sc1", "", 2, 10).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!((changes[0].name.as_str(), changes[0].write.at), ("pass", Timestamp(expires_at)));
+        assert_eq!(changes[0].write.value.to_json(), json!(null));
+    }
+}
@@ -1,4 +1,4 @@
-use super::errors::ServerError;
+use crate::errors::ServerError;
 use actix_web::{body::Body, web::{HttpResponse, Json}};
 use serde::Serialize;
 
@@ -0,0 +1,30 @@
+use actix_web::{web, HttpResponse};
+
+use crate::config::Config;
+use crate::hold::{self, HoldRequest};
+
+/// Handler to call hold::fetch
+pub async fn get_hold(
+    id: web::Path<String>,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    hold::fetch(config, id.into_inner()).await
+}
+
+/// Handler to call hold::place
+pub async fn put_hold(
+    id: web::Path<String>,
+    body: web::Json<HoldRequest>,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    hold::place(config, id.into_inner(), body.into_inner()).await
+}
+
+/// Handler to call hold::release
+pub async fn delete_hold(
+    id: web::Path<String>,
+    query: web::Query<HoldRequest>,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    hold::release(config, id.into_inner(), query.into_inner()).await
+}
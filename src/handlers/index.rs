@@ -0,0 +1,19 @@
+use actix_web::{web, HttpResponse};
+
+use crate::config::Config;
+use crate::index;
+
+/// Handler to call index::list
+pub async fn get_indexes(
+    config: web::Data<Config>,
+) -> HttpResponse {
+    index::list(config).await
+}
+
+/// Handler to call index::create
+pub async fn put_index(
+    path: web::Path<String>,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    index::create(config, path.into_inner()).await
+}
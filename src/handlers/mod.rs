@@ -1,3 +1,8 @@
 pub mod health;
 pub mod packet;
+pub mod schema;
+pub mod index;
+pub mod hold;
+pub mod tag;
+pub mod value;
 pub mod helpers;
\ No newline at end of file
@@ -1,28 +1,93 @@
 use actix_multipart::Multipart;
-use actix_session::Session;
-use actix_web::web::{Json, self};
-use serde::{Deserialize, Serialize};
+use actix_web::{web, HttpRequest, HttpResponse};
 
 use crate::config::Config;
-use crate::packet;
-use crate::errors::ServerError;
-use crate::handlers::helpers::respond_json;
-
-#[derive(Debug, Deserialize, Serialize, PartialEq)]
-pub struct PacketResponse {
-    pub status: String,
-    pub message: String,
-}
+use crate::packet::{self, MetadataEdit};
+use crate::database::level_0::{ListFilter, SearchQuery};
 
 /// Handler to call packet::receive
 pub async fn post_packet(
+    req: HttpRequest,
+    payload: Multipart,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    packet::receive(req, config, payload).await
+}
+
+/// Handler to call packet::receive_batch
+pub async fn post_batch(
+    req: HttpRequest,
     payload: Multipart,
-    session: Session,
-    config: web::Data<Config>,
-) -> Result<Json<PacketResponse>, ServerError> {
-    packet::receive(session, config, payload).await;
-    respond_json(PacketResponse {
-        status: "ok".into(),
-        message: "Packet received".into(),
-    })
-}
\ No newline at end of file
+    config: web::Data<Config>,
+) -> HttpResponse {
+    packet::receive_batch(req, config, payload).await
+}
+
+/// Handler to call packet::receive_raw
+pub async fn post_raw(
+    req: HttpRequest,
+    payload: web::Payload,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    packet::receive_raw(req, config, payload).await
+}
+
+/// Handler to call packet::list
+pub async fn get_all(
+    req: HttpRequest,
+    query: web::Query<ListFilter>,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    packet::list(req, config, query.into_inner()).await
+}
+
+/// Handler to call packet::search
+pub async fn get_search(
+    query: web::Query<SearchQuery>,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    packet::search(config, query.into_inner()).await
+}
+
+/// Handler to call packet::fetch
+pub async fn get_one(
+    req: HttpRequest,
+    id: web::Path<String>,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    packet::fetch(req, config, id.into_inner()).await
+}
+
+/// Handler to call packet::restore
+pub async fn restore_one(
+    id: web::Path<String>,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    packet::restore(config, id.into_inner()).await
+}
+
+/// Handler to call packet::patch_metadata
+pub async fn patch_metadata(
+    id: web::Path<String>,
+    query: web::Query<MetadataEdit>,
+    body: web::Bytes,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    packet::patch_metadata(config, id.into_inner(), query.into_inner(), body).await
+}
+
+/// Handler to call packet::metadata_history
+pub async fn get_metadata_history(
+    id: web::Path<String>,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    packet::metadata_history(config, id.into_inner()).await
+}
+
+/// Handler to call packet::remove
+pub async fn delete_one(
+    id: web::Path<String>,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    packet::remove(config, id.into_inner()).await
+}
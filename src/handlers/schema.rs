@@ -0,0 +1,37 @@
+use actix_web::{web, HttpResponse};
+use serde_json::Value;
+
+use crate::config::Config;
+use crate::schema;
+
+/// Handler to call schema::list
+pub async fn get_schemas(
+    config: web::Data<Config>,
+) -> HttpResponse {
+    schema::list(config).await
+}
+
+/// Handler to call schema::fetch
+pub async fn get_schema(
+    filetype: web::Path<String>,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    schema::fetch(config, filetype.into_inner()).await
+}
+
+/// Handler to call schema::register
+pub async fn put_schema(
+    filetype: web::Path<String>,
+    body: web::Json<Value>,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    schema::register(config, filetype.into_inner(), body.into_inner()).await
+}
+
+/// Handler to call schema::remove
+pub async fn delete_schema(
+    filetype: web::Path<String>,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    schema::remove(config, filetype.into_inner()).await
+}
@@ -0,0 +1,22 @@
+use actix_web::{web, HttpResponse};
+
+use crate::config::Config;
+use crate::tag::{self, TagQuery, TagRequest};
+
+/// Handler to call tag::add
+pub async fn post_tags(
+    id: web::Path<String>,
+    body: web::Json<TagRequest>,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    tag::add(config, id.into_inner(), body.into_inner()).await
+}
+
+/// Handler to call tag::remove
+pub async fn delete_tags(
+    id: web::Path<String>,
+    query: web::Query<TagQuery>,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    tag::remove(config, id.into_inner(), query.into_inner().into()).await
+}
@@ -0,0 +1,73 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+
+use crate::config::Config;
+use crate::database::single_value::HistoryQuery;
+use crate::value::{self, IncrementQuery, Namespace, ValueQuery, ValueRequest, WatchQuery};
+
+/// Handler to call value::list
+pub async fn get_values(
+    namespace: web::Query<Namespace>,
+    query: web::Query<ValueQuery>,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    value::list(config, namespace.into_inner().namespace, query.into_inner()).await
+}
+
+/// Handler to call value::fetch
+pub async fn get_value(
+    name: web::Path<String>,
+    namespace: web::Query<Namespace>,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    value::fetch(config, namespace.into_inner().namespace, name.into_inner()).await
+}
+
+/// Handler to call value::store
+pub async fn put_value(
+    req: HttpRequest,
+    name: web::Path<String>,
+    namespace: web::Query<Namespace>,
+    body: web::Json<ValueRequest>,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    value::store(req, config, namespace.into_inner().namespace, name.into_inner(), body.into_inner()).await
+}
+
+/// Handler to call value::increment
+pub async fn post_increment(
+    name: web::Path<String>,
+    namespace: web::Query<Namespace>,
+    query: web::Query<IncrementQuery>,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    value::increment(config, namespace.into_inner().namespace, name.into_inner(), query.into_inner()).await
+}
+
+/// Handler to call value::remove
+pub async fn delete_value(
+    name: web::Path<String>,
+    namespace: web::Query<Namespace>,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    value::remove(config, namespace.into_inner().namespace, name.into_inner()).await
+}
+
+/// Handler to call value::history
+pub async fn get_value_history(
+    name: web::Path<String>,
+    namespace: web::Query<Namespace>,
+    query: web::Query<HistoryQuery>,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    value::history(config, namespace.into_inner().namespace, name.into_inner(), query.into_inner()).await
+}
+
+/// Handler to call value::watch
+pub async fn get_watch(
+    req: HttpRequest,
+    namespace: web::Query<Namespace>,
+    query: web::Query<WatchQuery>,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    value::watch(req, config, namespace.into_inner().namespace, query.into_inner()).await
+}
@@ -0,0 +1,79 @@
+use crate::database;
+use crate::database::legal_hold::{self, HoldEvent};
+use crate::database::level_0;
+
+use super::config::Config;
+
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+/// Who is placing or releasing a legal hold, and why.
+#[derive(Deserialize, Debug)]
+pub struct HoldRequest {
+    pub actor: String,
+    pub reason: Option<String>,
+}
+
+/// A packet's legal hold, and its history.
+#[derive(Serialize, Debug)]
+struct HoldStatus {
+    held: bool,
+    history: Vec<HoldEvent>,
+}
+
+/// Fetches whether packet `uuid` is under legal hold, and who has placed and
+/// released holds on it, or 404s if there is no such packet.
+pub async fn fetch(config: web::Data<Config>, uuid: String) -> HttpResponse {
+    let result = database::connection::open(config.db.as_path()).and_then(|conn| {
+        match level_0::get(&conn, &uuid)? {
+            Some(packet) => Ok(Some(HoldStatus { held: packet.held, history: legal_hold::history(&conn, &uuid)? })),
+            None => Ok(None),
+        }
+    });
+
+    match result {
+        Ok(Some(status)) => HttpResponse::Ok().json(status),
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(error) => HttpResponse::InternalServerError().body(format!("{:?}", error)),
+    }
+}
+
+/// Puts packet `uuid` under legal hold. 409s if it already is.
+pub async fn place(config: web::Data<Config>, uuid: String, request: HoldRequest) -> HttpResponse {
+    set(&config, &uuid, request, true)
+}
+
+/// Lifts the legal hold on packet `uuid`. 409s if there isn't one.
+///
+/// Who and why come from `?actor=&reason=`, since clients and proxies are
+/// apt to drop the body of a `DELETE`.
+pub async fn release(config: web::Data<Config>, uuid: String, request: HoldRequest) -> HttpResponse {
+    set(&config, &uuid, request, false)
+}
+
+fn set(config: &Config, uuid: &str, request: HoldRequest, held: bool) -> HttpResponse {
+    if request.actor.trim().is_empty() {
+        return HttpResponse::BadRequest().body("actor must not be empty");
+    }
+
+    let result = config.clock.now().map_err(|error| format!("{:?}", error)).and_then(|now| {
+        database::connection::open(config.db.as_path())
+            .and_then(|conn| {
+                let reason = request.reason.as_deref();
+                if held {
+                    legal_hold::place(&conn, uuid, &request.actor, reason, now)
+                } else {
+                    legal_hold::release(&conn, uuid, &request.actor, reason, now)
+                }
+            })
+            .map_err(|error| format!("{:?}", error))
+    });
+
+    match result {
+        Ok(Some(true)) => HttpResponse::NoContent().finish(),
+        Ok(Some(false)) if held => HttpResponse::Conflict().body("packet is already under legal hold"),
+        Ok(Some(false)) => HttpResponse::Conflict().body("packet is not under legal hold"),
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(error) => HttpResponse::InternalServerError().body(error),
+    }
+}
@@ -0,0 +1,38 @@
+use crate::database;
+use crate::database::level_0;
+use crate::database::metadata_index;
+
+use super::config::Config;
+
+use actix_web::{web, HttpResponse};
+
+/// Lists the metadata paths that have an index.
+pub async fn list(config: web::Data<Config>) -> HttpResponse {
+    let result = database::connection::open(config.db.as_path())
+        .and_then(|conn| metadata_index::list(&conn));
+
+    match result {
+        Ok(indexes) => HttpResponse::Ok().json(indexes),
+        Err(error) => HttpResponse::InternalServerError().body(format!("{:?}", error)),
+    }
+}
+
+/// Gives metadata `path` (e.g. `camera.exposure`) an indexed generated column
+/// on `level_0`, so `meta.<path>` filters on it don't have to parse every
+/// packet's metadata. Does nothing if the path already has one.
+pub async fn create(config: web::Data<Config>, path: String) -> HttpResponse {
+    if !level_0::is_metadata_path(&path) {
+        return HttpResponse::BadRequest().body(format!("{:?} is not a metadata path", path));
+    }
+
+    let result = config.clock.now().map_err(|error| format!("{:?}", error)).and_then(|now| {
+        database::connection::open(config.db.as_path())
+            .and_then(|conn| metadata_index::create(&conn, &path, now))
+            .map_err(|error| format!("{:?}", error))
+    });
+
+    match result {
+        Ok(index) => HttpResponse::Ok().json(index),
+        Err(error) => HttpResponse::InternalServerError().body(error),
+    }
+}
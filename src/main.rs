@@ -1,16 +1,28 @@
 mod config;
 mod util;
+mod timestamp;
+mod clock;
+mod merge_patch;
+mod notifier;
 
 pub mod server;
 // pub mod database;
 pub mod handlers;
 pub mod errors;
 pub mod packet;
+pub mod schema;
+pub mod index;
+pub mod retention;
+pub mod expiry;
+pub mod hold;
+pub mod tag;
+pub mod value;
 pub mod database;
+#[cfg(test)]
+mod testing;
 
 use std::error::Error;
 use std::path::Path;
-use std::path::PathBuf;
 
 use log::{error, info};
 
@@ -18,20 +30,21 @@ use log::{error, info};
 // use clap::ArgMatches;
 use config::Config;
 
-fn define_config() -> Config {
-    Config {
-        ip: "127.0.0.1".to_string(),
-        port: 8000,
-        app_name: "TLM Server".to_string(),
-        db: PathBuf::from("./tlm.db"),
-        test_db: PathBuf::from("./test.db"),
+/// Where the server config is read from, when it's there.
+const CONFIG_FILE: &str = "config.toml";
+
+/// The server config: `config.toml` if there is one, with anything it leaves
+/// out taken from `Config::default`.
+fn define_config() -> Result<Config, Box<dyn Error>> {
+    if !Path::new(CONFIG_FILE).exists() {
+        return Ok(Config::default());
     }
+    Ok(toml::from_str(&util::load_string(CONFIG_FILE)?)?)
 }
 
 fn main() {
-    match err_main() {
-        Err(e) => error!("error: {:?}", e),
-        Ok(_) => (),
+    if let Err(e) = err_main() {
+        error!("error: {:?}", e);
     }
 }
 
@@ -45,11 +58,25 @@ async fn err_main() -> Result<(), Box<dyn Error>> {
     
     // define server config
     // this will also take care of initializing from cli
-    let config = define_config();
+    let config = define_config()?;
+
+    // create db and bring its schema up to date
+    let conn = database::connection::open(config.db.as_path())?;
+    database::migrations::apply_all(&conn)?;
+
+    // let retention hand the space it frees back a little at a time
+    if database::retention::enable_incremental_vacuum(&conn)? {
+        info!("Switched {:?} to incremental auto-vacuum", config.db);
+    }
+
+    // make sure there's somewhere to spool uploads
+    std::fs::create_dir_all(&config.file_tmp_path)?;
+
+    // purge packets past their retention, until the server stops
+    let _retention = retention::start(config.clone());
 
-    // create db
-    // dotenv().ok();
-    // db::create_db("DATABSE_URL");
+    // sweep out single values past their expiry, likewise
+    let _value_sweep = expiry::start(config.clone());
 
     // start the server
     info!("Starting server...");
@@ -0,0 +1,84 @@
+//! JSON Merge Patch (RFC 7386).
+
+use serde_json::{Map, Value};
+
+/// Applies merge `patch` to `target`: objects merge key by key, a `null`
+/// removes a key, and anything else replaces the value outright.
+pub fn apply(target: &mut Value, patch: &Value) {
+    let patch = match patch {
+        Value::Object(patch) => patch,
+        patch => {
+            *target = patch.clone();
+            return;
+        }
+    };
+
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    let target = target.as_object_mut().unwrap();
+
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            apply(target.entry(key.as_str()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+/// The merge patch that turns `from` into `to`.
+///
+/// Merge patches can't set a key to `null`, so a `null` in `to` comes out as
+/// the key being removed.
+pub fn diff(from: &Value, to: &Value) -> Value {
+    let (from, to) = match (from, to) {
+        (Value::Object(from), Value::Object(to)) => (from, to),
+        (_, to) => return to.clone(),
+    };
+
+    let mut patch = Map::new();
+    for key in from.keys().filter(|key| !to.contains_key(*key)) {
+        patch.insert(key.clone(), Value::Null);
+    }
+    for (key, value) in to {
+        match from.get(key) {
+            Some(previous) if previous == value => (),
+            Some(previous) => {
+                patch.insert(key.clone(), diff(previous, value));
+            }
+            None => {
+                patch.insert(key.clone(), value.clone());
+            }
+        }
+    }
+
+    Value::Object(patch)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn it_applies_and_diffs_merge_patches() {
+        let original = json!({"filename": "a.bin", "camera": {"gain": 2, "mode": "raw"}, "tags": ["x"]});
+        let patch = json!({"camera": {"gain": 3, "mode": null}, "tags": ["y"], "note": "recalibrated"});
+
+        let mut patched = original.clone();
+        apply(&mut patched, &patch);
+        assert_eq!(
+            patched,
+            json!({"filename": "a.bin", "camera": {"gain": 3}, "tags": ["y"], "note": "recalibrated"}),
+        );
+
+        assert_eq!(diff(&original, &patched), patch);
+        assert_eq!(diff(&patched, &patched), json!({}));
+
+        let mut replaced = original;
+        apply(&mut replaced, &json!("scalar"));
+        assert_eq!(replaced, json!("scalar"));
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+/// Wakes whoever is waiting on a change, e.g. a watch on single values.
+///
+/// It only says that something changed, not what: waiters look that up
+/// themselves. Clones share the same waiters.
+#[derive(Clone, Default)]
+pub struct ChangeNotifier(Arc<Mutex<Changes>>);
+
+#[derive(Default)]
+struct Changes {
+    /// Counts the changes announced so far.
+    generation: u64,
+    /// The waiters, by the id each `Changed` was given when first polled.
+    waiting: HashMap<u64, Waker>,
+    next_id: u64,
+}
+
+impl ChangeNotifier {
+    /// Counts the changes announced so far. Read it before looking for
+    /// changes, then wait on `changed` with it, and nothing announced in
+    /// between is missed.
+    pub fn generation(&self) -> u64 {
+        self.0.lock().unwrap().generation
+    }
+
+    /// Announces a change, waking everyone waiting.
+    pub fn notify(&self) {
+        let mut changes = self.0.lock().unwrap();
+        changes.generation += 1;
+        for (_, waker) in changes.waiting.drain() {
+            waker.wake();
+        }
+    }
+
+    /// Resolves once a change is announced after `generation`.
+    pub fn changed(&self, generation: u64) -> Changed {
+        Changed { notifier: self.clone(), generation, id: None }
+    }
+}
+
+impl fmt::Debug for ChangeNotifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ChangeNotifier")
+    }
+}
+
+/// The future returned by `ChangeNotifier::changed`. Dropping it stops the
+/// wait.
+pub struct Changed {
+    notifier: ChangeNotifier,
+    generation: u64,
+    /// Where its waker is kept among the waiters, once it has been polled.
+    id: Option<u64>,
+}
+
+impl Future for Changed {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = self.get_mut();
+        let mut changes = this.notifier.0.lock().unwrap();
+        if changes.generation != this.generation {
+            return Poll::Ready(());
+        }
+        let id = match this.id {
+            Some(id) => id,
+            None => {
+                changes.next_id += 1;
+                changes.next_id
+            }
+        };
+        this.id = Some(id);
+        changes.waiting.insert(id, cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for Changed {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            self.notifier.0.lock().unwrap().waiting.remove(&id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::FutureExt;
+
+    use super::*;
+
+    #[test]
+    fn it_wakes_waiters_on_a_change() {
+        let notifier = ChangeNotifier::default();
+        let generation = notifier.generation();
+
+        let mut changed = notifier.changed(generation);
+        assert!((&mut changed).now_or_never().is_none());
+        notifier.clone().notify();
+        assert!(changed.now_or_never().is_some());
+
+        assert_eq!(notifier.generation(), generation + 1);
+        assert!(notifier.changed(generation).now_or_never().is_some());
+    }
+
+    #[test]
+    fn it_forgets_waiters_that_give_up() {
+        let notifier = ChangeNotifier::default();
+        let waiting = || notifier.0.lock().unwrap().waiting.len();
+
+        for _ in 0..3 {
+            let mut changed = notifier.changed(notifier.generation());
+            assert!((&mut changed).now_or_never().is_none());
+            assert!((&mut changed).now_or_never().is_none());
+            assert_eq!(waiting(), 1);
+        }
+        assert_eq!(waiting(), 0);
+    }
+}
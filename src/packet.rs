@@ -3,62 +3,352 @@
 // mod util;
 // mod database;
 
-use crate::{util, database};
+use crate::{database, merge_patch, schema};
+use crate::database::context::TLM_LEVEL_0_TABLE;
+use crate::database::idempotency::{self, Reservation, StoredReply};
+use crate::database::level_0::{self, Change, ListFilter, MetaFilter, PacketSummary, SearchQuery};
+use crate::database::metadata_history::{self, MetadataRevision, Revision};
+use crate::database::metadata_schema;
+use crate::timestamp::Timestamp;
 
 use super::config::Config;
 // use super::database;
 
-use std::path::Path;
-use actix_multipart::{Multipart, MultipartError};
-use actix_session::Session;
-use actix_web::{web, HttpResponse, Result};
-use futures_util::TryStreamExt;
-use rusqlite::DatabaseName;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use actix_multipart::{Field, Multipart, MultipartError};
+use actix_web::{error, error::{BlockingError, PayloadError, QueryPayloadError}, http::{header, StatusCode}, web, HttpRequest, HttpResponse, Result};
+use futures_util::{stream, Future, Stream, TryStreamExt};
+use jsonschema::JSONSchema;
+use log::error;
+use rusqlite::{params, Connection, DatabaseName, TransactionBehavior};
+use sha2::{Digest, Sha256};
 use serde::{Serialize, Deserialize};
 use serde_json;
 use uuid::Uuid;
 
 
 /// Receives a Multipart payload and saves it to the database.
-pub async fn receive(session: Session, config: web::Data<Config>, payload: Multipart) -> HttpResponse {
+pub async fn receive(req: HttpRequest, config: web::Data<Config>, payload: Multipart) -> HttpResponse {
+    let key = header_str(&req, header::HeaderName::from_static(IDEMPOTENCY_KEY)).map(str::to_string);
 
-    // Save the uploaded packet to the database
-    let result = save(session, config, payload).await;
+    // Save the uploaded packet to the database, once per idempotency key
+    let upload = save(config.clone(), payload);
+    idempotently(&config, "/packets", key, upload).await
+}
 
-    // Return a JSON response with the result of the save operation
-    match result {
-        Ok(success_value) => HttpResponse::Ok().json(success_value),
-        Err(error) => HttpResponse::InternalServerError().body(format!("{:?}", error)),
+/// Receives a batch of packets as indexed `metadata[n]` and `packet[n]` (and
+/// optionally `checksum[n]`) multipart fields, and saves them all in a single
+/// transaction.
+///
+/// Each item stands or falls on its own, and the response lists every item's
+/// outcome so partial failures are visible.
+pub async fn receive_batch(req: HttpRequest, config: web::Data<Config>, payload: Multipart) -> HttpResponse {
+    let key = header_str(&req, header::HeaderName::from_static(IDEMPOTENCY_KEY)).map(str::to_string);
+
+    let upload = save_batch(config.clone(), payload);
+    idempotently(&config, "/packets/batch", key, async { upload.await.map(|results| BatchSaved { results }) }).await
+}
+
+/// Receives a packet as a raw `application/octet-stream` body, for producers
+/// that can't build multipart requests, and saves it to the database.
+///
+/// Metadata comes from `X-Tlm-<field>` headers or `meta.<field>` query
+/// parameters (headers win), and goes through the same validation as a
+/// multipart upload.
+/// A `checksum` or `sha256` query parameter, or a `Content-Digest` header,
+/// is checked against the body.
+pub async fn receive_raw(req: HttpRequest, config: web::Data<Config>, payload: web::Payload) -> HttpResponse {
+    let key = header_str(&req, header::HeaderName::from_static(IDEMPOTENCY_KEY)).map(str::to_string);
+
+    let upload = save_raw(req, config.clone(), payload);
+    idempotently(&config, "/packets/raw", key, upload).await
+}
+
+/// Runs `upload` at most once per `Idempotency-Key` sent to `endpoint`.
+///
+/// A retry sent with the same key within `idempotency_window_secs` gets the
+/// first attempt's reply back rather than storing the packet again; one that
+/// arrives while the first attempt is still running gets a 409. An attempt
+/// that never finishes, because the client went away part way through, frees
+/// the key for the retry, and one cut short by the server going down holds it
+/// for `idempotency_lease_secs` at most. Requests without a key just run.
+async fn idempotently<T, F>(config: &Config, endpoint: &str, key: Option<String>, upload: F) -> HttpResponse
+where
+    T: Serialize,
+    F: Future<Output = Result<T, SaveError>>,
+{
+    let key = match key {
+        Some(key) => key,
+        None => return Reply::new(upload.await).into_response(),
+    };
+    if key.is_empty() || key.len() > MAX_IDEMPOTENCY_KEY_LEN {
+        return HttpResponse::BadRequest()
+            .body(format!("Idempotency-Key must be 1 to {} characters", MAX_IDEMPOTENCY_KEY_LEN));
     }
+
+    let reserved = match reserve_idempotency_key(config, endpoint, &key) {
+        Ok(Reservation::Reserved) => ReservedKey { config, endpoint, key: &key, settled: false },
+        Ok(Reservation::InProgress) => {
+            return HttpResponse::Conflict().body("a request with this Idempotency-Key is still in progress")
+        }
+        Ok(Reservation::Completed(reply)) => {
+            return HttpResponse::build(StatusCode::from_u16(reply.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR))
+                .content_type(reply.content_type)
+                .header(IDEMPOTENT_REPLAYED, "true")
+                .body(reply.body)
+        }
+        Err(error) => return HttpResponse::InternalServerError().body(format!("{:?}", error)),
+    };
+
+    let reply = Reply::new(upload.await);
+    reserved.settle(&reply);
+
+    reply.into_response()
+}
+
+/// An idempotency key held by the request running under it. It is released
+/// when dropped before it's settled, as happens when the client disconnects
+/// and actix drops the request part way through.
+struct ReservedKey<'a> {
+    config: &'a Config,
+    endpoint: &'a str,
+    key: &'a str,
+    settled: bool,
 }
 
-async fn save(session: Session, config: web::Data<Config>, mut payload: Multipart) -> Result<(), SaveError> {
+impl ReservedKey<'_> {
+    /// Records `reply` against the key, so retries get it back.
+    fn settle(mut self, reply: &Reply) {
+        self.settled = true;
+
+        // A server error isn't the client's fault, so leave the key free for a retry.
+        let recorded = database::connection::open(self.config.db.as_path()).and_then(|conn| {
+            if reply.status.is_server_error() {
+                idempotency::release(&conn, self.endpoint, self.key)
+            } else {
+                idempotency::complete(&conn, self.endpoint, self.key, &StoredReply {
+                    status: reply.status.as_u16(),
+                    content_type: reply.content_type.to_string(),
+                    body: reply.body.clone(),
+                })
+            }
+        });
+        if let Err(error) = recorded {
+            error!("failed to record the reply for Idempotency-Key {:?}: {:?}", self.key, error);
+        }
+    }
+}
+
+impl Drop for ReservedKey<'_> {
+    fn drop(&mut self) {
+        if self.settled {
+            return;
+        }
+        let released = database::connection::open(self.config.db.as_path())
+            .and_then(|conn| idempotency::release(&conn, self.endpoint, self.key));
+        if let Err(error) = released {
+            error!("failed to release Idempotency-Key {:?}: {:?}", self.key, error);
+        }
+    }
+}
+
+fn reserve_idempotency_key(config: &Config, endpoint: &str, key: &str) -> Result<Reservation, SaveError> {
     let conn = database::connection::open(config.db.as_path())?;
+    let now = config.clock.now()?;
+    let window = config.idempotency_window_secs as i64 * 1000;
+    let lease = config.idempotency_lease_secs as i64 * 1000;
+    Ok(idempotency::reserve(&conn, endpoint, key, now, now - window, now - lease)?)
+}
 
-    let (metadata, packet) = extract_files(payload).await?;
+/// The status code and message a client sees for a failed save.
+fn describe_save_error(error: &SaveError) -> (StatusCode, String) {
+    match error {
+        SaveError::ExtractError(ExtractError::PacketTooLarge(max)) => (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("packet exceeds the maximum size of {} bytes", max),
+        ),
+        SaveError::ExtractError(error @ ExtractError::MissingMetadata)
+        | SaveError::ExtractError(error @ ExtractError::MissingPacket)
+        | SaveError::ExtractError(error @ ExtractError::QueryError(_))
+        | SaveError::ExtractError(error @ ExtractError::HeaderError(_)) => {
+            (StatusCode::BAD_REQUEST, format!("{:?}", error))
+        }
+        SaveError::ExtractError(ExtractError::JsonError(error)) => {
+            (StatusCode::BAD_REQUEST, format!("invalid metadata: {}", error))
+        }
+        SaveError::ExtractError(ExtractError::InvalidChecksum(reason)) => {
+            (StatusCode::BAD_REQUEST, format!("invalid checksum: {}", reason))
+        }
+        SaveError::InvalidMetadata { filetype, violations } => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("metadata does not match the schema for filetype {:?}:\n{}", filetype, violations.join("\n")),
+        ),
+        SaveError::ChecksumMismatch { expected, actual } => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("checksum mismatch: expected sha256 {} but the packet received hashes to {}", expected, actual),
+        ),
+        SaveError::Missing => (StatusCode::NOT_FOUND, "no such packet".to_string()),
+        SaveError::Held => (StatusCode::CONFLICT, "packet is under legal hold".to_string()),
+        error => (StatusCode::INTERNAL_SERVER_ERROR, format!("{:?}", error)),
+    }
+}
+
+async fn save(config: web::Data<Config>, payload: Multipart) -> Result<Saved, SaveError> {
+    // `Content-Digest` covers the whole multipart body, not the packet, so
+    // only the `checksum` field can vouch for it.
+    let upload = extract_files(payload, &config).await?;
+    save_upload(config, upload).await
+}
+
+async fn save_raw(req: HttpRequest, config: web::Data<Config>, mut payload: web::Payload) -> Result<Saved, SaveError> {
+    let mut upload = extract_raw(&req, &mut payload, &config).await?;
+
+    if upload.checksum.is_none() {
+        upload.checksum = header_str(&req, header::HeaderName::from_static(CONTENT_DIGEST))
+            .map(parse_content_digest)
+            .transpose()?;
+    }
+
+    save_upload(config, upload).await
+}
+
+/// Stores a single upload in a transaction of its own, on the blocking
+/// threadpool since copying the packet in can take a while.
+async fn save_upload(config: web::Data<Config>, upload: Upload) -> Result<Saved, SaveError> {
+    web::block(move || {
+        let mut conn = database::connection::open(config.db.as_path())?;
+        // Take the write lock up front so the duplicate check and the insert can't interleave with another upload.
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let saved = store(&tx, &config, &mut Schemas::new(), &upload)?;
+        tx.commit()?;
+
+        Ok(saved)
+    })
+    .await
+    .map_err(SaveError::from)
+}
+
+async fn save_batch(config: web::Data<Config>, payload: Multipart) -> Result<Vec<BatchItem>, SaveError> {
+    let uploads = extract_batch(payload, &config).await?;
+
+    web::block(move || {
+        let mut conn = database::connection::open(config.db.as_path())?;
+        let mut tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        // Batches are often all one filetype; compile its schema once.
+        let mut schemas = Schemas::new();
+        let mut results = Vec::with_capacity(uploads.len());
+        for (index, upload) in uploads {
+            let result = upload.map_err(SaveError::from).and_then(|upload| {
+                // A savepoint per item, so one that fails part way through leaves nothing behind.
+                let sp = tx.savepoint()?;
+                let saved = store(&sp, &config, &mut schemas, &upload)?;
+                sp.commit()?;
+                Ok(saved)
+            });
+            results.push(BatchItem::new(index, result));
+        }
+        tx.commit()?;
+
+        Ok(results)
+    })
+    .await
+    .map_err(SaveError::from)
+}
+
+/// Verifies and writes one upload to `level_0` on `conn`, which the caller
+/// holds a write transaction on. Metadata schemas are compiled into `schemas`
+/// as they're needed.
+fn store(conn: &Connection, config: &Config, schemas: &mut Schemas, upload: &Upload) -> Result<Saved, SaveError> {
     let uuid = Uuid::new_v4().to_string();
-    let now = util::now()?;
+    let now = config.clock.now()?;
+
+    let metadata: serde_json::Value = serde_json::from_str(&upload.metadata_json).map_err(ExtractError::from)?;
+    check_metadata(conn, schemas, &upload.metadata.filetype, &metadata)?;
+
+    // Digest of the packet bytes, served back as the download `ETag`.
+    let sha256 = upload.packet.sha256.clone();
+
+    // Catch corruption on the way up before it's stored.
+    if let Some(expected) = &upload.checksum {
+        if *expected != sha256 {
+            return Err(SaveError::ChecksumMismatch { expected: expected.clone(), actual: sha256 });
+        }
+    }
+
+    // Ground stations re-send packets after reconnecting; hand back the copy we already have.
+    if !config.allow_duplicate_packets {
+        if let Some(uuid) = level_0::find_by_sha256(conn, &sha256)? {
+            return Ok(Saved { status: "duplicate", uuid });
+        }
+    }
 
-    // write to sqlite.
-    let (insert_stmt, params) = create_insert_stmt(&packet);
-    conn.execute(insert_stmt.as_str(), &params)?;
+    // write to sqlite, reserving room for the packet with a ZEROBLOB.
+    let insert_stmt = create_insert_stmt(upload.packet.size);
+    conn.execute(
+        insert_stmt.as_str(),
+        params![
+            uuid,
+            now,
+            upload.metadata.observed_at,
+            upload.metadata_json,
+            upload.metadata.filename,
+            upload.metadata.filetype,
+            sha256,
+        ],
+    )?;
 
     // Get the row id off the BLOB we just inserted.
     let rowid = conn.last_insert_rowid();
-    // Open the BLOB we just inserted for IO.
-    let mut blob = conn.blob_open(DatabaseName::Main, "level0blobs", "packet", rowid, false)?;
+    // Open the BLOB we just inserted for IO and copy the spooled packet into it.
+    let mut blob = conn.blob_open(DatabaseName::Main, TLM_LEVEL_0_TABLE, "packet", rowid, false)?;
+    let mut file = File::open(&upload.packet.path)?;
+    io::copy(&mut file, &mut blob)?;
 
-    blob.write_at(&packet);
+    Ok(Saved { status: "ok", uuid })
+}
+
+/// The metadata schemas compiled while handling one request, by filetype,
+/// so each is compiled once however many packets it checks. `None` stands
+/// for a filetype without a schema.
+type Schemas = HashMap<String, Option<JSONSchema>>;
+
+/// Checks `metadata` against the JSON Schema registered for `filetype`, if one
+/// is, compiling it into `schemas` unless it's there already. Filetypes
+/// without a schema take any metadata.
+fn check_metadata(conn: &Connection, schemas: &mut Schemas, filetype: &str, metadata: &serde_json::Value) -> Result<(), SaveError> {
+    if !schemas.contains_key(filetype) {
+        let compiled = match metadata_schema::get(conn, filetype)? {
+            Some(registered) => Some(schema::compile(&registered.schema).map_err(SaveError::InvalidSchema)?),
+            None => None,
+        };
+        schemas.insert(filetype.to_string(), compiled);
+    }
+    let compiled = match &schemas[filetype] {
+        Some(compiled) => compiled,
+        None => return Ok(()),
+    };
+
+    let violations = schema::validate(compiled, metadata);
+    if !violations.is_empty() {
+        return Err(SaveError::InvalidMetadata { filetype: filetype.to_string(), violations });
+    }
 
     Ok(())
 }
 
-/// Extracts the metadata and packet parts from the multipart payload.
-async fn extract_files(mut payload: Multipart) -> Result<(Metadata, Vec<u8>), ExtractError> {
+/// Extracts the metadata and packet parts, and the client's `checksum` (or
+/// `sha256`) of the packet if it sent one, from the multipart payload.
+///
+/// The packet is spooled to a temp file under `file_tmp_path` rather than held
+/// in memory, and rejected once it grows past `max_packet_size`.
+async fn extract_files(mut payload: Multipart, config: &Config) -> Result<Upload, ExtractError> {
 
-    // Initialize two vectors to store the metadata and packet data
-    let mut metadata_vec = Vec::new();
-    let mut packet_vec = Vec::new();
+    // Collect the parts of the upload as they come off the stream
+    let mut parts = UploadParts::default();
 
     // Iterate over each field in the multipart payload
     while let Some(mut field) = payload.try_next().await? {
@@ -69,64 +359,716 @@ async fn extract_files(mut payload: Multipart) -> Result<(Metadata, Vec<u8>), Ex
         // Get the name of the field, or return an error if it is not found
         let filename = content_disposition.get_name().unwrap_or("Fieldname not found");
 
-        // If the field is the `metadata` or `packet` field, add it to the corresponding vector
-        if filename == "metadata" {
-            while let Some(chunk) = field.try_next().await? {
-                metadata_vec.extend(chunk);
-            }
-        } else if filename == "packet" {
-            while let Some(chunk) = field.try_next().await? {
-                packet_vec.extend(chunk);
+        // Buffer the `metadata` and `checksum` fields; spool the `packet` field to disk
+        match filename {
+            "metadata" => read_field(&mut field, &mut parts.metadata).await?,
+            "checksum" | "sha256" => read_field(&mut field, &mut parts.checksum).await?,
+            "packet" => parts.packet = Some(spool_packet(&mut field, config).await?),
+            _ => (),
+        }
+    }
+
+    parts.parse()
+}
+
+/// Extracts the indexed fields of a batch upload, grouped into one upload per
+/// index and in index order.
+///
+/// Anything wrong with a single item is kept with that item rather than
+/// failing the batch; only a broken multipart stream fails it outright.
+async fn extract_batch(mut payload: Multipart, config: &Config) -> Result<Vec<(usize, Result<Upload, ExtractError>)>, ExtractError> {
+    let mut batch: BTreeMap<usize, UploadParts> = BTreeMap::new();
+
+    while let Some(mut field) = payload.try_next().await? {
+        let content_disposition = field.content_disposition().unwrap();
+        let fieldname = content_disposition.get_name().unwrap_or("Fieldname not found");
+
+        // Fields without an index aren't part of any item
+        let (filename, index) = match parse_indexed_name(fieldname) {
+            Some(indexed) => indexed,
+            None => continue,
+        };
+        let parts = batch.entry(index).or_default();
+
+        match filename {
+            "metadata" => read_field(&mut field, &mut parts.metadata).await?,
+            "checksum" | "sha256" => read_field(&mut field, &mut parts.checksum).await?,
+            "packet" => match spool_packet(&mut field, config).await {
+                Ok(packet) => parts.packet = Some(packet),
+                Err(ExtractError::MultipartError(error)) => return Err(error.into()),
+                Err(error) => parts.failed = Some(error),
+            },
+            _ => (),
+        }
+    }
+
+    Ok(batch.into_iter().map(|(index, parts)| (index, parts.parse())).collect())
+}
+
+/// Extracts a raw-body upload: the packet is the body, and the metadata and
+/// checksum come from the query string and `X-Tlm-*` headers.
+async fn extract_raw(req: &HttpRequest, payload: &mut web::Payload, config: &Config) -> Result<Upload, ExtractError> {
+    let mut parts = UploadParts::default();
+
+    let query = web::Query::<HashMap<String, String>>::from_query(req.query_string())?.into_inner();
+    if let Some(checksum) = query.get("checksum").or_else(|| query.get("sha256")) {
+        parts.checksum = checksum.clone().into_bytes();
+    }
+
+    // Round-trip through the same parsing as a multipart `metadata` field
+    parts.metadata = serde_json::to_vec(&raw_metadata(req, query)?)?;
+    parts.packet = Some(spool_packet(payload, config).await?);
+
+    parts.parse()
+}
+
+/// The metadata of a raw upload, from its `meta.<field>` query parameters and
+/// `X-Tlm-<field>` headers; any other parameters are left alone.
+///
+/// Header names arrive lowercased, so fields with capitals in their names have
+/// to go in the query. Each value is read as JSON, so numbers and booleans
+/// keep their type, and is a string otherwise: a string that reads as JSON,
+/// like `42`, has to be sent quoted.
+fn raw_metadata(req: &HttpRequest, query: HashMap<String, String>) -> Result<serde_json::Map<String, serde_json::Value>, ExtractError> {
+    let value = |value: &str| serde_json::from_str(value).unwrap_or_else(|_| serde_json::Value::String(value.to_string()));
+
+    let mut metadata = serde_json::Map::new();
+    for (name, text) in &query {
+        if let Some(name) = name.strip_prefix(METADATA_QUERY_PREFIX) {
+            metadata.insert(name.to_string(), value(text));
+        }
+    }
+    for (name, text) in req.headers() {
+        if let Some(name) = name.as_str().strip_prefix(METADATA_HEADER_PREFIX) {
+            metadata.insert(name.to_string(), value(text.to_str()?));
+        }
+    }
+    Ok(metadata)
+}
+
+/// Splits a batch field name like `packet[3]` into `("packet", 3)`.
+fn parse_indexed_name(fieldname: &str) -> Option<(&str, usize)> {
+    let (filename, index) = fieldname.strip_suffix(']')?.split_once('[')?;
+    Some((filename, index.parse().ok()?))
+}
+
+/// Reads the whole of a (small) multipart field onto the end of `into`.
+async fn read_field(field: &mut Field, into: &mut Vec<u8>) -> Result<(), MultipartError> {
+    while let Some(chunk) = field.try_next().await? {
+        into.extend(chunk);
+    }
+    Ok(())
+}
+
+/// The raw parts of one packet upload, as read off the multipart stream.
+#[derive(Default)]
+struct UploadParts {
+    metadata: Vec<u8>,
+    checksum: Vec<u8>,
+    packet: Option<TempPacket>,
+    /// Why the packet couldn't be read, if it couldn't.
+    failed: Option<ExtractError>,
+}
+
+impl UploadParts {
+    /// Checks the parts are all there and parses them into an `Upload`.
+    fn parse(self) -> Result<Upload, ExtractError> {
+        if let Some(error) = self.failed {
+            return Err(error);
+        }
+
+        // If metadata or packet are empty, nothing was found. Return the corresponding error
+        if self.metadata.is_empty() {
+            return Err(ExtractError::MissingMetadata);
+        }
+        let packet = match self.packet {
+            Some(packet) if packet.size > 0 => packet,
+            _ => return Err(ExtractError::MissingPacket),
+        };
+
+        // Convert the metadata vector to a string
+        let metadata_str = String::from_utf8(self.metadata)?;
+        // Parse the metadata string as a `Metadata` struct, which checks the core fields are there
+        let metadata: Metadata  = serde_json::from_str(&metadata_str)?;
+
+        // Normalize the checksum, if one was sent
+        let checksum = if self.checksum.is_empty() {
+            None
+        } else {
+            Some(parse_hex_sha256(std::str::from_utf8(&self.checksum)?)?)
+        };
+
+        Ok(Upload { metadata, metadata_json: metadata_str, packet, checksum })
+    }
+}
+
+/// Checks a client-sent hex sha256 is well formed, and lowercases it.
+fn parse_hex_sha256(checksum: &str) -> Result<String, ExtractError> {
+    let checksum = checksum.trim();
+    if checksum.len() != 64 || !checksum.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(ExtractError::InvalidChecksum(format!("{:?} is not a hex sha256", checksum)));
+    }
+    Ok(checksum.to_ascii_lowercase())
+}
+
+/// Pulls the `sha-256` entry out of a `Content-Digest` header (RFC 9530),
+/// e.g. `sha-256=:<base64>:`, as hex. It is the digest of the whole body, so
+/// it only vouches for raw uploads, whose body is the packet.
+fn parse_content_digest(content_digest: &str) -> Result<String, ExtractError> {
+    for entry in content_digest.split(',') {
+        let (algorithm, digest) = match entry.split_once('=') {
+            Some((algorithm, digest)) => (algorithm.trim(), digest.trim()),
+            None => continue,
+        };
+        if !algorithm.eq_ignore_ascii_case("sha-256") {
+            continue;
+        }
+
+        let bytes = digest
+            .strip_prefix(':')
+            .and_then(|digest| digest.strip_suffix(':'))
+            .and_then(|digest| base64::decode(digest).ok())
+            .filter(|bytes| bytes.len() == 32)
+            .ok_or_else(|| ExtractError::InvalidChecksum(format!("malformed Content-Digest {:?}", digest)))?;
+
+        return Ok(hex(&bytes));
+    }
+
+    Err(ExtractError::InvalidChecksum("Content-Digest has no sha-256 entry".into()))
+}
+
+/// `bytes` as lowercase hex, the way sha256s are stored.
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Streams a multipart field or request body into a new temp file, hashing it
+/// on the way, and giving up as soon as it exceeds `max_packet_size`.
+async fn spool_packet<S, E>(body: &mut S, config: &Config) -> Result<TempPacket, ExtractError>
+where
+    S: Stream<Item = Result<web::Bytes, E>> + Unpin,
+    ExtractError: From<E>,
+{
+    let path = config.file_tmp_path.join(Uuid::new_v4().to_string());
+
+    // File::create is blocking operation, use threadpool
+    let create_path = path.clone();
+    let mut f = web::block(move || File::create(create_path)).await?;
+
+    // From here on the temp file is removed however we leave.
+    let mut packet = TempPacket { path, size: 0, sha256: String::new() };
+    let mut hasher = Sha256::new();
+
+    // Body in turn is stream of *Bytes* object
+    while let Some(chunk) = body.try_next().await? {
+        packet.size += chunk.len() as u64;
+        if packet.size > config.max_packet_size {
+            return Err(ExtractError::PacketTooLarge(config.max_packet_size));
+        }
+        hasher.update(&chunk);
+
+        // filesystem operations are blocking, we have to use threadpool
+        f = web::block(move || f.write_all(&chunk).map(|_| f)).await?;
+    }
+
+    packet.sha256 = hex(&hasher.finalize());
+    Ok(packet)
+}
+
+/// Lists the packets in the database matching `filter`, one page at a time.
+///
+/// Any `meta.<path>` query parameters filter on the packets' metadata too.
+pub async fn list(req: HttpRequest, config: web::Data<Config>, mut filter: ListFilter) -> HttpResponse {
+    filter.meta = match parse_meta_filters(req.query_string()) {
+        Ok(meta) => meta,
+        Err(error) => return HttpResponse::BadRequest().body(error),
+    };
+
+    match list_page(&config, &filter) {
+        Ok(page) => HttpResponse::Ok().json(page),
+        Err(error) => HttpResponse::InternalServerError().body(format!("{:?}", error)),
+    }
+}
+
+/// Picks the `meta.`-prefixed filters out of a query string.
+fn parse_meta_filters(query_string: &str) -> Result<Vec<MetaFilter>, String> {
+    let pairs = web::Query::<Vec<(String, String)>>::from_query(query_string)
+        .map_err(|error| format!("{}", error))?
+        .into_inner();
+
+    pairs
+        .iter()
+        .filter_map(|(name, value)| MetaFilter::parse(name, value))
+        .collect()
+}
+
+fn list_page(config: &Config, filter: &ListFilter) -> Result<PacketPage, rusqlite::Error> {
+    let conn = database::connection::open(config.db.as_path())?;
+    let packets = level_0::list(&conn, filter)?;
+
+    // A full page means there may be more; hand back the cursor for the next one.
+    let next = if packets.len() as u32 == filter.limit() {
+        packets.last().map(|packet| packet.id)
+    } else {
+        None
+    };
+
+    Ok(PacketPage { packets, next })
+}
+
+/// Searches packet metadata, best matches first, with a snippet of each match.
+pub async fn search(config: web::Data<Config>, query: SearchQuery) -> HttpResponse {
+    if query.q.trim().is_empty() {
+        return HttpResponse::BadRequest().body("q must not be empty");
+    }
+
+    let result = database::connection::open(config.db.as_path())
+        .and_then(|conn| level_0::search(&conn, &query));
+
+    match result {
+        Ok(hits) => HttpResponse::Ok().json(hits),
+        // The SQL is fixed, so a plain SQLITE_ERROR is FTS5 rejecting the query.
+        Err(rusqlite::Error::SqliteFailure(error, Some(message))) if error.code == rusqlite::ErrorCode::Unknown => {
+            HttpResponse::BadRequest().body(format!("invalid search query: {}", message))
+        }
+        Err(error) => HttpResponse::InternalServerError().body(format!("{:?}", error)),
+    }
+}
+
+/// Longest `Idempotency-Key` we'll hold on to.
+const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
+/// `Idempotency-Key` and `Idempotent-Replayed` aren't among actix's known headers either.
+const IDEMPOTENCY_KEY: &str = "idempotency-key";
+const IDEMPOTENT_REPLAYED: &str = "idempotent-replayed";
+
+/// Prefixes of the headers and query parameters a raw upload carries its
+/// metadata in.
+const METADATA_HEADER_PREFIX: &str = "x-tlm-";
+const METADATA_QUERY_PREFIX: &str = "meta.";
+
+/// `Content-Digest` isn't among actix's known headers.
+const CONTENT_DIGEST: &str = "content-digest";
+
+/// Size of the chunks a packet BLOB is streamed out in.
+const PACKET_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Fetches a single packet: its metadata as JSON, or its packet bytes when the
+/// client accepts `application/octet-stream`.
+pub async fn fetch(req: HttpRequest, config: web::Data<Config>, uuid: String) -> HttpResponse {
+    let result = database::connection::open(config.db.as_path())
+        .and_then(|conn| level_0::get(&conn, &uuid).map(|packet| (conn, packet)));
+
+    match result {
+        Ok((_, None)) => HttpResponse::NotFound().finish(),
+        Ok((conn, Some(packet))) if accepts_raw(&req) => download(&req, conn, packet),
+        Ok((_, Some(packet))) => HttpResponse::Ok().json(packet),
+        Err(error) => HttpResponse::InternalServerError().body(format!("{:?}", error)),
+    }
+}
+
+/// Serves a packet's bytes, honouring a single-part `Range` (with `If-Range`)
+/// and `If-None-Match` against an `ETag` of the packet's stored sha256.
+fn download(req: &HttpRequest, conn: Connection, packet: PacketSummary) -> HttpResponse {
+    let size = packet.size as usize;
+    let etag = etag(&packet);
+
+    if let Some(if_none_match) = header_str(req, header::IF_NONE_MATCH) {
+        if etag_matches(if_none_match, &etag) {
+            return HttpResponse::NotModified().header(header::ETAG, etag).finish();
+        }
+    }
+
+    // A stale `If-Range` means the client's partial copy is of something else,
+    // so it gets the whole packet instead.
+    let range = match header_str(req, header::IF_RANGE) {
+        Some(if_range) if if_range != etag => None,
+        _ => header_str(req, header::RANGE),
+    };
+
+    match parse_byte_range(range, size) {
+        ByteRange::Partial(start, end) => HttpResponse::PartialContent()
+            .header(header::ETAG, etag)
+            .header(header::ACCEPT_RANGES, "bytes")
+            .header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end - 1, size))
+            .content_type("application/octet-stream")
+            .no_chunking((end - start) as u64)
+            .streaming(stream_packet(conn, packet.id, start, end)),
+        ByteRange::Unsatisfiable => HttpResponse::RangeNotSatisfiable()
+            .header(header::CONTENT_RANGE, format!("bytes */{}", size))
+            .finish(),
+        ByteRange::Full => HttpResponse::Ok()
+            .header(header::ETAG, etag)
+            .header(header::ACCEPT_RANGES, "bytes")
+            .content_type("application/octet-stream")
+            .no_chunking(size as u64)
+            .streaming(stream_packet(conn, packet.id, 0, size)),
+    }
+}
+
+fn accepts_raw(req: &HttpRequest) -> bool {
+    header_str(req, header::ACCEPT).is_some_and(|accept| accept.contains("application/octet-stream"))
+}
+
+fn header_str(req: &HttpRequest, name: header::HeaderName) -> Option<&str> {
+    req.headers().get(name).and_then(|value| value.to_str().ok())
+}
+
+/// Packets never change once stored, so their sha256 makes a strong `ETag`.
+/// Packets stored before digests were kept fall back to their uuid.
+fn etag(packet: &PacketSummary) -> String {
+    format!("\"{}\"", packet.sha256.as_deref().unwrap_or(&packet.uuid))
+}
+
+/// Whether an `If-None-Match` header value matches `etag`.
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match.trim() == "*"
+        || if_none_match
+            .split(',')
+            .any(|candidate| candidate.trim().trim_start_matches("W/") == etag)
+}
+
+/// How much of a packet a `Range` header asks for.
+#[derive(Debug, PartialEq)]
+enum ByteRange {
+    /// No usable range; send everything.
+    Full,
+    /// The bytes in `start..end`.
+    Partial(usize, usize),
+    /// A range that lies entirely outside the packet.
+    Unsatisfiable,
+}
+
+/// Parses a `Range` header against a packet of `size` bytes.
+///
+/// Only single `bytes=` ranges are served partially; anything else is ignored,
+/// which the spec allows, and gets the full packet.
+fn parse_byte_range(range: Option<&str>, size: usize) -> ByteRange {
+    let spec = match range.and_then(|range| range.trim().strip_prefix("bytes=")) {
+        Some(spec) if !spec.contains(',') => spec,
+        _ => return ByteRange::Full,
+    };
+    let (first, last) = match spec.split_once('-') {
+        Some((first, last)) => (first.trim(), last.trim()),
+        None => return ByteRange::Full,
+    };
+
+    match (first, last) {
+        ("", "") => ByteRange::Full,
+        // `bytes=-n` is the last n bytes.
+        ("", suffix) => match suffix.parse::<usize>() {
+            Ok(0) => ByteRange::Unsatisfiable,
+            Ok(_) if size == 0 => ByteRange::Unsatisfiable,
+            Ok(suffix) => ByteRange::Partial(size.saturating_sub(suffix), size),
+            Err(_) => ByteRange::Full,
+        },
+        (first, last) => {
+            let start = match first.parse::<usize>() {
+                Ok(start) => start,
+                Err(_) => return ByteRange::Full,
+            };
+            let end = match last {
+                "" => size,
+                last => match last.parse::<usize>() {
+                    Ok(last) if last >= start => (last + 1).min(size),
+                    _ => return ByteRange::Full,
+                },
+            };
+            if start >= size {
+                ByteRange::Unsatisfiable
+            } else {
+                ByteRange::Partial(start, end)
             }
         }
     }
+}
+
+/// Moves a single packet to the trash, or 404s if there is no such packet.
+/// It can be restored until it has been there `trash_grace_secs`.
+///
+/// A packet under legal hold can't be deleted; that's a 409.
+pub async fn remove(config: web::Data<Config>, uuid: String) -> HttpResponse {
+    let result = config.clock.now().map_err(|error| format!("{:?}", error)).and_then(|now| {
+        database::connection::open(config.db.as_path())
+            .and_then(|conn| level_0::trash(&conn, &uuid, now))
+            .map_err(|error| format!("{:?}", error))
+    });
 
-    // If metadata or packet vectors are empty, nothing was found. Return the corresponding error
-    if metadata_vec.is_empty() {
-        return Err(ExtractError::MissingMetadata);
+    match result {
+        Ok(Change::Made) => HttpResponse::NoContent().finish(),
+        Ok(Change::Missing) => HttpResponse::NotFound().finish(),
+        Ok(Change::Held) => HttpResponse::Conflict().body("packet is under legal hold"),
+        Err(error) => HttpResponse::InternalServerError().body(error),
     }
-    if packet_vec.is_empty() {
-        return Err(ExtractError::MissingPacket);
+}
+
+/// Takes a packet back out of the trash, or 404s if it isn't in there.
+pub async fn restore(config: web::Data<Config>, uuid: String) -> HttpResponse {
+    let result = database::connection::open(config.db.as_path())
+        .and_then(|conn| level_0::restore(&conn, &uuid));
+
+    match result {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => HttpResponse::NotFound().finish(),
+        Err(error) => HttpResponse::InternalServerError().body(format!("{:?}", error)),
     }
+}
 
-    // Convert the metadata vector to a string
-    let metadata_str = String::from_utf8(metadata_vec)?;
-    // Parse the metadata string as a `Metadata` struct
-    let metadata: Metadata  = serde_json::from_str(&metadata_str)?;
-    // Convert the packet vector to a `Vec<u8>` -> (into itself?...)
-    let packet = packet_vec.into();
+/// Who is editing a packet's metadata.
+#[derive(Deserialize, Debug)]
+pub struct MetadataEdit {
+    pub author: String,
+}
 
-    // Return an Ok with a tuple containing the metadata and packet
-    Ok((metadata, packet))
+/// One version of a packet's metadata, with the merge patch that made it from
+/// the version before.
+#[derive(Serialize, Debug)]
+struct HistoryEntry {
+    #[serde(flatten)]
+    revision: MetadataRevision,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    patch: Option<serde_json::Value>,
 }
 
-fn create_insert_stmt(packet: &[u8]) -> String {
-    format!("insert into level0blobs (uuid, createdate, metadata, packet) values (?, ?, ?, ZEROBLOB({}))", packet.len())
+/// Edits a packet's metadata with a JSON merge patch (RFC 7386), keeping the
+/// version it replaces in the packet's history. The packet bytes never change.
+///
+/// The edited metadata is held to the same rules as an upload's: it needs a
+/// `filename` and `filetype`, and must match the schema registered for its
+/// filetype. A packet under legal hold can't be edited; that's a 409.
+pub async fn patch_metadata(config: web::Data<Config>, uuid: String, edit: MetadataEdit, body: web::Bytes) -> HttpResponse {
+    if edit.author.trim().is_empty() {
+        return HttpResponse::BadRequest().body("author must not be empty");
+    }
+    let patch: serde_json::Value = match serde_json::from_slice(&body) {
+        Ok(patch) => patch,
+        Err(error) => return HttpResponse::BadRequest().body(format!("invalid merge patch: {}", error)),
+    };
+
+    Reply::new(revise_metadata(&config, &uuid, &edit.author, &patch)).into_response()
+}
+
+fn revise_metadata(config: &Config, uuid: &str, author: &str, patch: &serde_json::Value) -> Result<MetadataRevision, SaveError> {
+    let now = config.clock.now()?;
+    let mut conn = database::connection::open(config.db.as_path())?;
+    // Hold the write lock from reading the current version until the new one is in.
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+    let current = metadata_history::current(&tx, uuid)?.ok_or(SaveError::Missing)?;
+    if current.held {
+        return Err(SaveError::Held);
+    }
+
+    let mut metadata = current.revision.metadata.clone();
+    merge_patch::apply(&mut metadata, patch);
+    let fields: Metadata = serde_json::from_value(metadata.clone()).map_err(ExtractError::from)?;
+    check_metadata(&tx, &mut Schemas::new(), &fields.filetype, &metadata)?;
+
+    let revision = metadata_history::revise(&tx, uuid, &current.revision, &Revision {
+        metadata: &metadata,
+        filename: &fields.filename,
+        filetype: &fields.filetype,
+        observed_at: fields.observed_at,
+        author,
+        at: now,
+    })?;
+    tx.commit()?;
+
+    Ok(revision)
+}
+
+/// Fetches every version of a packet's metadata, oldest first, each after the
+/// first with the merge patch that made it. 404s if there is no such packet.
+pub async fn metadata_history(config: web::Data<Config>, uuid: String) -> HttpResponse {
+    let result = database::connection::open(config.db.as_path())
+        .and_then(|conn| metadata_history::history(&conn, &uuid));
+
+    match result {
+        Ok(Some(revisions)) => {
+            let patches: Vec<_> = revisions
+                .windows(2)
+                .map(|pair| Some(merge_patch::diff(&pair[0].metadata, &pair[1].metadata)))
+                .collect();
+            let entries: Vec<_> = revisions
+                .into_iter()
+                .zip(std::iter::once(None).chain(patches))
+                .map(|(revision, patch)| HistoryEntry { revision, patch })
+                .collect();
+            HttpResponse::Ok().json(entries)
+        }
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(error) => HttpResponse::InternalServerError().body(format!("{:?}", error)),
+    }
+}
+
+/// Streams bytes `start..end` of packet `id`'s BLOB out of the db a chunk at a
+/// time, so the whole packet is never held in memory. Each chunk is read on
+/// the blocking thread pool, which `conn` is handed to and back from.
+fn stream_packet(
+    conn: Connection,
+    id: i64,
+    start: usize,
+    end: usize,
+) -> impl Stream<Item = Result<web::Bytes, actix_web::Error>> + Unpin {
+    Box::pin(stream::unfold(Some((conn, start)), move |state| async move {
+        let (conn, offset) = state?;
+        if offset >= end {
+            return None;
+        }
+
+        let read = web::block(move || {
+            let mut chunk = vec![0u8; PACKET_CHUNK_SIZE.min(end - offset)];
+            let read = level_0::read_packet_at(&conn, id, &mut chunk, offset)?;
+            chunk.truncate(read);
+            Ok::<_, rusqlite::Error>((conn, chunk))
+        })
+        .await;
+        match read {
+            Ok((_, chunk)) if chunk.is_empty() => None,
+            Ok((conn, chunk)) => {
+                let next = offset + chunk.len();
+                Some((Ok(web::Bytes::from(chunk)), Some((conn, next))))
+            }
+            // Surface the error once, then end the stream.
+            Err(e) => Some((Err(error::ErrorInternalServerError(e)), None)),
+        }
+    }))
+}
+
+fn create_insert_stmt(packet_size: u64) -> String {
+    format!("insert into {} (uuid, createdate, received_at, observed_at, metadata, filename, filetype, sha256, packet) values (?1, ?2, ?2, ?3, ?4, ?5, ?6, ?7, ZEROBLOB({}))", TLM_LEVEL_0_TABLE, packet_size)
 }
 
+/// Packet metadata: the core fields every packet must have, which are also
+/// stored in their own indexed columns of `level_0`, plus whatever else the
+/// client sends.
 #[derive(Deserialize, Serialize, Debug)]
 struct Metadata {
     filename: String,
     filetype: String,
+    /// When the packet's data was observed, as ms since the epoch or ISO-8601.
+    #[serde(default)]
+    observed_at: Option<Timestamp>,
+    /// Any other fields, which are kept but not interpreted.
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
-struct Packet {
-    uuid: String,
-    createdate: String,
+/// A packet upload spooled to disk. The file is removed when this is dropped.
+#[derive(Debug)]
+struct TempPacket {
+    path: PathBuf,
+    size: u64,
+    /// Hex sha256 of the packet bytes, taken while spooling them.
+    sha256: String,
+}
+
+impl Drop for TempPacket {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// The response to an upload, in a form that can be stored and replayed.
+#[derive(Debug)]
+struct Reply {
+    status: StatusCode,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Reply {
+    fn new<T: Serialize>(result: Result<T, SaveError>) -> Self {
+        match result.map(|saved| serde_json::to_string(&saved)) {
+            Ok(Ok(body)) => Reply { status: StatusCode::OK, content_type: "application/json", body },
+            Ok(Err(error)) => Reply {
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                content_type: "text/plain; charset=utf-8",
+                body: format!("{:?}", error),
+            },
+            Err(error) => {
+                let (status, body) = describe_save_error(&error);
+                Reply { status, content_type: "text/plain; charset=utf-8", body }
+            }
+        }
+    }
+
+    fn into_response(self) -> HttpResponse {
+        HttpResponse::build(self.status).content_type(self.content_type).body(self.body)
+    }
+}
+
+/// A packet upload, spooled and parsed and ready to store.
+#[derive(Debug)]
+struct Upload {
     metadata: Metadata,
-    packet: Vec<u8>,
+    /// The metadata exactly as the client sent it, which is what gets stored.
+    metadata_json: String,
+    packet: TempPacket,
+    /// The sha256 the client says the packet has.
+    checksum: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+struct Saved {
+    status: &'static str,
+    uuid: String,
+}
+
+#[derive(Serialize, Debug)]
+struct BatchSaved {
+    results: Vec<BatchItem>,
+}
+
+/// The outcome of saving one item of a batch upload.
+#[derive(Serialize, Debug)]
+struct BatchItem {
+    index: usize,
+    /// `ok`, `duplicate` or `error`
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uuid: Option<String>,
+    /// The status code the item would have got as a single upload, on error.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl BatchItem {
+    fn new(index: usize, result: Result<Saved, SaveError>) -> Self {
+        match result {
+            Ok(saved) => BatchItem { index, status: saved.status, uuid: Some(saved.uuid), code: None, error: None },
+            Err(error) => {
+                let (code, message) = describe_save_error(&error);
+                BatchItem { index, status: "error", uuid: None, code: Some(code.as_u16()), error: Some(message) }
+            }
+        }
+    }
 }
 
+#[derive(Serialize, Debug)]
+struct PacketPage {
+    packets: Vec<PacketSummary>,
+    next: Option<i64>,
+}
+
+// Fields are only read through `Debug`, when an error is reported.
+#[allow(dead_code)]
 #[derive(Debug)]
 enum ExtractError {
     Utf8Error(std::str::Utf8Error),
     FromUtf8Error(std::string::FromUtf8Error),
     MultipartError(MultipartError),
-    // BlockingError(BlockingError<T>),
+    PayloadError(PayloadError),
+    QueryError(QueryPayloadError),
+    HeaderError(header::ToStrError),
+    IoError(std::io::Error),
     JsonError(serde_json::Error),
     MissingMetadata,
     MissingPacket,
+    PacketTooLarge(u64),
+    InvalidChecksum(String),
 }
 
 impl From<std::str::Utf8Error> for ExtractError {
@@ -153,11 +1095,56 @@ impl From<MultipartError> for ExtractError {
     }
 }
 
+impl From<PayloadError> for ExtractError {
+    fn from(error: PayloadError) -> Self {
+        ExtractError::PayloadError(error)
+    }
+}
+
+impl From<QueryPayloadError> for ExtractError {
+    fn from(error: QueryPayloadError) -> Self {
+        ExtractError::QueryError(error)
+    }
+}
+
+impl From<header::ToStrError> for ExtractError {
+    fn from(error: header::ToStrError) -> Self {
+        ExtractError::HeaderError(error)
+    }
+}
+
+impl From<std::io::Error> for ExtractError {
+    fn from(error: std::io::Error) -> Self {
+        ExtractError::IoError(error)
+    }
+}
+
+impl From<BlockingError<std::io::Error>> for ExtractError {
+    fn from(error: BlockingError<std::io::Error>) -> Self {
+        match error {
+            BlockingError::Error(error) => ExtractError::IoError(error),
+            BlockingError::Canceled => ExtractError::IoError(io::Error::other("blocking operation canceled")),
+        }
+    }
+}
+
+#[allow(dead_code)]
 #[derive(Debug)]
 enum SaveError {
     DbError(rusqlite::Error),
     ExtractError(ExtractError),
-    // UtilError(UtilError),
+    IoError(std::io::Error),
+    /// Kept as text so a `SaveError` can cross threads.
+    UtilError(String),
+    ChecksumMismatch { expected: String, actual: String },
+    /// The metadata breaks the JSON Schema registered for its filetype.
+    InvalidMetadata { filetype: String, violations: Vec<String> },
+    /// The JSON Schema registered for the filetype can't be compiled.
+    InvalidSchema(String),
+    /// There's no such packet, or it's in the trash.
+    Missing,
+    /// The packet is under legal hold, so it can't change.
+    Held,
 }
 
 impl From<rusqlite::Error> for SaveError {
@@ -172,210 +1159,239 @@ impl From<ExtractError> for SaveError {
     }
 }
 
-// impl From<UtilError> for SaveError {
-//     fn from(error: UtilError) -> Self {
-//         SaveError::UtilError(error)
-//     }
-// }
-
-async fn receive_file_old(session: Session, config: web::Data<Config>, mut payload: Multipart) -> HttpResponse {
-    match save_file_too(session, config, payload).await {
-        Ok(r) => HttpResponse::Ok().json(r),
-        Err(e) => return HttpResponse::InternalServerError().body(format!("{:?}", e)),
+impl From<std::io::Error> for SaveError {
+    fn from(error: std::io::Error) -> Self {
+        SaveError::IoError(error)
     }
 }
 
-async fn save_file_too(session: Session, config: web::Data<Config>, payload: Multipart) -> Result<(), SaveError> {
-    let conn = database::open_connection(config.db.as_path())?;
-    let temp_path = config.file_tmp_path.clone();
+impl From<Box<dyn std::error::Error>> for SaveError {
+    fn from(error: Box<dyn std::error::Error>) -> Self {
+        SaveError::UtilError(error.to_string())
+    }
+}
 
-    match save_file(&temp_path, payload).await {
-        Ok((name, file_pathp)) => Ok(()),
-        Err(error) => Err(SaveError::UtilError(error)),
+impl From<BlockingError<SaveError>> for SaveError {
+    fn from(error: BlockingError<SaveError>) -> Self {
+        match error {
+            BlockingError::Error(error) => error,
+            BlockingError::Canceled => SaveError::IoError(io::Error::other("blocking operation canceled")),
+        }
     }
 }
 
-async fn save_file(to_dir: &Path, mut payload: Multipart) -> Result<(String, String), ExtractError> {
-    // iterate over multipart stream
-    // ONLY SAVING THE FIRST FILE
-    while let Some(mut field) = payload.try_next().await? {
-        // A multipart/form-data stream has to contain `content_disposition`
-        let content_disposition = field
-            .content_disposition()
-            .ok_or(simple_error::SimpleError::new("bad"))?;
+#[cfg(test)]
+mod tests {
+    use futures_util::{future, FutureExt};
+
+    use super::*;
+    use crate::testing::TestConfig;
+
+    #[test]
+    fn it_parses_byte_ranges() {
+        assert_eq!(parse_byte_range(None, 10), ByteRange::Full);
+        assert_eq!(parse_byte_range(Some("bytes=2-5"), 10), ByteRange::Partial(2, 6));
+        assert_eq!(parse_byte_range(Some("bytes=2-"), 10), ByteRange::Partial(2, 10));
+        assert_eq!(parse_byte_range(Some("bytes=2-99"), 10), ByteRange::Partial(2, 10));
+        assert_eq!(parse_byte_range(Some("bytes=-3"), 10), ByteRange::Partial(7, 10));
+        assert_eq!(parse_byte_range(Some("bytes=10-"), 10), ByteRange::Unsatisfiable);
+        assert_eq!(parse_byte_range(Some("bytes=-0"), 10), ByteRange::Unsatisfiable);
+        assert_eq!(parse_byte_range(Some("bytes=0-1,4-5"), 10), ByteRange::Full);
+        assert_eq!(parse_byte_range(Some("bytes=5-2"), 10), ByteRange::Full);
+        assert_eq!(parse_byte_range(Some("items=0-1"), 10), ByteRange::Full);
+    }
 
-        let filename = content_disposition
-            .get_filename()
-            .unwrap_or("filename not found");
+    #[test]
+    fn it_matches_etags() {
+        assert!(etag_matches("*", "\"abc\""));
+        assert!(etag_matches("\"xyz\", W/\"abc\"", "\"abc\""));
+        assert!(!etag_matches("\"xyz\"", "\"abc\""));
+    }
+
+    #[test]
+    fn it_parses_client_checksums() {
+        // sha256("hello")
+        let hex = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        assert_eq!(parse_hex_sha256(&hex.to_uppercase()).unwrap(), hex);
+        assert!(parse_hex_sha256("abc").is_err());
+
+        let header = "sha-512=:AAAA:, sha-256=:LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=:";
+        assert_eq!(parse_content_digest(header).unwrap(), hex);
+        assert!(parse_content_digest("sha-256=:nope:").is_err());
+        assert!(parse_content_digest("sha-512=:AAAA:").is_err());
+    }
 
-        let wkfilename = Uuid::new_v4().to_string();
+    #[test]
+    fn it_keeps_extra_metadata_fields() {
+        let metadata: Metadata = serde_json::from_str(
+            r#"{"filename": "a.bin", "filetype": "image", "name": "Jack", "age": 30}"#,
+        )
+        .unwrap();
+        assert_eq!(metadata.filetype, "image");
+        assert_eq!(metadata.extra["age"], 30);
+        assert_eq!(metadata.observed_at, None);
+
+        let observed: Metadata = serde_json::from_str(
+            r#"{"filename": "a.bin", "filetype": "image", "observed_at": "2024-05-01T12:00:00.250Z"}"#,
+        )
+        .unwrap();
+        assert_eq!(observed.observed_at, Some(Timestamp(1714564800250)));
+        assert!(serde_json::from_str::<Metadata>(r#"{"filename": "a", "filetype": "b", "observed_at": "noon"}"#).is_err());
+
+        assert!(serde_json::from_str::<Metadata>(r#"{"name": "Jack"}"#).is_err());
+        assert!(serde_json::from_str::<Metadata>(r#"["a.bin", "image"]"#).is_err());
+    }
 
-        let mut filepath = to_dir.to_path_buf();
-        filepath.push(wkfilename);
+    /// An upload of `bytes` as a `log` packet, spooled to a temp file.
+    fn upload(bytes: &[u8]) -> Upload {
+        let path = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        std::fs::write(&path, bytes).unwrap();
+        let metadata_json = r#"{"filename": "a.bin", "filetype": "log"}"#.to_string();
+        Upload {
+            metadata: serde_json::from_str(&metadata_json).unwrap(),
+            metadata_json,
+            packet: TempPacket { path, size: bytes.len() as u64, sha256: hex(&Sha256::digest(bytes)) },
+            checksum: None,
+        }
+    }
 
-        let rf = filepath.clone();
+    #[test]
+    fn it_hands_back_duplicates_unless_they_are_allowed() {
+        let conn = Connection::open_in_memory().unwrap();
+        database::migrations::apply_all(&conn).unwrap();
+        let mut config = Config::default();
+        let mut schemas = Schemas::new();
+
+        let first = store(&conn, &config, &mut schemas, &upload(b"packet")).unwrap();
+        assert_eq!(first.status, "ok");
+        let again = store(&conn, &config, &mut schemas, &upload(b"packet")).unwrap();
+        assert_eq!((again.status, &again.uuid), ("duplicate", &first.uuid));
+        assert_eq!(store(&conn, &config, &mut schemas, &upload(b"other")).unwrap().status, "ok");
+
+        // A copy in the trash doesn't count; the packet is stored afresh.
+        level_0::trash(&conn, &first.uuid, 0).unwrap();
+        let fresh = store(&conn, &config, &mut schemas, &upload(b"packet")).unwrap();
+        assert_eq!(fresh.status, "ok");
+        assert_ne!(fresh.uuid, first.uuid);
+
+        config.allow_duplicate_packets = true;
+        let copy = store(&conn, &config, &mut schemas, &upload(b"packet")).unwrap();
+        assert_eq!(copy.status, "ok");
+        assert_ne!(copy.uuid, fresh.uuid);
+        assert_eq!(level_0::get(&conn, &copy.uuid).unwrap().unwrap().size, 6);
+    }
 
-        // File::create is blocking operation, use threadpool
-        let mut f = web::block(|| std::fs::File::create(filepath)).await?;
+    #[test]
+    fn it_frees_the_idempotency_key_of_an_abandoned_upload() {
+        let test = TestConfig::new();
+        let key = || Some("k".to_string());
+        let saved = || async { Ok(Saved { status: "ok", uuid: "uuid-0".into() }) };
 
-        // Field in turn is stream of *Bytes* object
-        while let Some(chunk) = field.try_next().await? {
-            // filesystem operations are blocking, we have to use threadpool
-            f = web::block(move || f.write_all(&chunk).map(|_| f)).await?;
-        }
+        // The client goes away while the packet is still coming in.
+        let abandoned = idempotently(&test.config, "/packets", key(), future::pending::<Result<Saved, SaveError>>());
+        assert!(abandoned.now_or_never().is_none());
 
-        // stop on the first file.
-        let ps = rf
-            .into_os_string()
-            .into_string()
-            .map_err(|e| Err(e));
-        return Ok((filename.to_string(), ps?));
-    }
-
-    simple_error::bail!("no file saved")
-}
-
-
-// async fn receive_file(
-//     session: Session,
-//     config: web::Data<Config>,
-//     mut payload: Multipart,
-// ) -> HttpResponse {
-//     let result = save(session, config, payload).await;
-
-//     match result {
-//         Ok(success_value) => HttpResponse::Ok().json(success_value),
-//         Err(error) => HttpResponse::InternalServerError().body(format!("{:?}", error)),
-//     }
-// }
-
-// fn create_insert_stmt(packet: &[u8]) -> String {
-//     format!("insert into level0blobs (uuid, createdate, metadata, packet) values (?, ?, ?, ZEROBLOB({}))", packet.len())
-// }
-
-// async fn save(
-//     session: Session,
-//     config: web::Data<Config>,
-//     mut payload: Multipart,
-// ) -> Result<(), Box<dyn Error>> {
-//     let conn = database::open_connection(config.db.as_path())?;
-
-//     let (metadata, packet) = extract_files(payload).await?;
-//     let uuid = Uuid::new_v4().to_string();
-//     let now = util::now()?;
-
-//     // write to sqlite.
-//     let insert_stmt = create_insert_stmt(&packet);
-//     conn.execute(insert_stmt.as_str(), params![uuid, now, metadata])?;
-
-//     // Get the row id off the BLOB we just inserted.
-//     let rowid = conn.last_insert_rowid();
-//     // Open the BLOB we just inserted for IO.
-//     let mut blob = conn.blob_open(DatabaseName::Main, "level0blobs", "packet", rowid, false)?;
-
-//     blob.write(&packet);
-
-//     Ok(())
-// }
-
-// #[derive(Debug, Deserialize, Serialize)]
-// struct Metadata {
-//     // fields go here
-// }
-
-// async fn extract_files(mut payload: Multipart) -> Result<(Metadata, Bytes), Box<dyn Error>> {
-//     let mut metadata = Vec::new();
-//     let mut packet = Vec::new();
-
-//     while let Some(mut field) = payload.try_next().await? {
-//         let content_disposition = field
-//             .content_disposition()
-//             .ok_or(simple_error::SimpleError::new("bad"))?;
-
-//         let filename = content_disposition
-//             .get_name()
-//             .unwrap_or("fieldname not found");
-
-//         if filename == "metadata" {
-//             while let Some(chunk) = field.try_next().await? {
-//                 metadata.extend(chunk);
-//             }
-//         } else if filename == "packet" {
-//             while let Some(chunk) = field.try_next().await? {
-//                 packet.extend(chunk);
-//             }
-//         }
-//     }
-
-//     let metadata = String::from_utf8(metadata)?;
-//     let metadata: Metadata = serde_json::from_str(&metadata)?;
-//     let packet = packet.into();
-
-//     Ok((metadata, packet))
-// }
-
-// async fn receive_file_old(
-//     session: Session,
-//     config: web::Data<Config>,
-//     mut payload: Multipart,
-// ) -> HttpResponse {
-//     match save_file_too(session, config, payload).await {
-//         Ok(r) => HttpResponse::Ok().json(r),
-//         Err(e) => return HttpResponse::InternalServerError().body(format!("{:?}", e)),
-//     }
-// }
-
-// async fn save_file_too(
-//     session: Session,
-//     config: web::Data<Config>,
-//     mut payload: Multipart,
-// ) -> Result<(), Box<dyn Error>> {
-//     let conn = database::open_connection(config.db.as_path())?;
-//     let tp = config.file_tmp_path.clone();
-//     let (name, fp) = save_file(&tp, payload).await?;
-//     Ok(())
-// }
-
-// async fn save_file(
-//     to_dir: &Path,
-//     mut payload: Multipart,
-// ) -> Result<(String, String), ExtractError> {
-//     // iterate over multipart stream
-
-//     // ONLY SAVING THE FIRST FILE
-//     while let Some(mut field) = payload.try_next().await? {
-//         // A multipart/form-data stream has to contain `content_disposition`
-//         let content_disposition = field
-//             .content_disposition()
-//             .ok_or(simple_error::SimpleError::new("bad"))?;
-
-//         let filename = content_disposition
-//             .get_filename()
-//             .unwrap_or("filename not found");
-
-//         let wkfilename = Uuid::new_v4().to_string();
-
-//         let mut filepath = to_dir.to_path_buf();
-//         filepath.push(wkfilename);
-
-//         let rf = filepath.clone();
-
-//         // File::create is blocking operation, use threadpool
-//         let mut f = web::block(|| std::fs::File::create(filepath)).await?;
-
-//         // Field in turn is stream of *Bytes* object
-//         while let Some(chunk) = field.try_next().await? {
-//             // filesystem operations are blocking, we have to use threadpool
-//             f = web::block(move || f.write_all(&chunk).map(|_| f)).await?;
-//         }
-
-//         // stop on the first file.
-//         let ps = rf
-//             .into_os_string()
-//             .into_string()
-//             .map_err(|osstr| simple_error!("couldn't convert filename to string"));
-//         return Ok((filename.to_string(), ps?));
-//     }
-
-//     simple_error::bail!("no file saved")
-// }
+        let retry = idempotently(&test.config, "/packets", key(), saved()).now_or_never().unwrap();
+        assert_eq!(retry.status(), StatusCode::OK);
+        assert!(retry.headers().get(IDEMPOTENT_REPLAYED).is_none());
+
+        let replayed = idempotently(&test.config, "/packets", key(), saved()).now_or_never().unwrap();
+        assert_eq!(replayed.headers().get(IDEMPOTENT_REPLAYED).unwrap(), "true");
+
+        // The same key sent to another endpoint is a different key.
+        let batch = idempotently(&test.config, "/packets/batch", key(), saved()).now_or_never().unwrap();
+        assert!(batch.headers().get(IDEMPOTENT_REPLAYED).is_none());
+    }
+
+    #[test]
+    fn it_takes_over_the_idempotency_key_of_an_upload_the_server_lost() {
+        let mut test = TestConfig::new();
+        test.config.idempotency_lease_secs = 60;
+        let send = || {
+            let saved = async { Ok(Saved { status: "ok", uuid: "uuid-0".into() }) };
+            idempotently(&test.config, "/packets", Some("k".to_string()), saved).now_or_never().unwrap().status()
+        };
+
+        // The server goes down mid-upload, leaving the key reserved.
+        let conn = database::connection::open(test.config.db.as_path()).unwrap();
+        idempotency::reserve(&conn, "/packets", "k", TestConfig::START, 0, 0).unwrap();
+
+        test.clock.advance(60 * 1000);
+        assert_eq!(send(), StatusCode::CONFLICT);
+        test.clock.advance(1);
+        assert_eq!(send(), StatusCode::OK);
+    }
+
+    #[test]
+    fn it_stamps_uploads_with_the_configured_clock() {
+        let test = TestConfig::new();
+        let conn = database::connection::open(test.config.db.as_path()).unwrap();
+
+        let saved = store(&conn, &test.config, &mut Schemas::new(), &upload(b"packet")).unwrap();
+        let packet = level_0::get(&conn, &saved.uuid).unwrap().unwrap();
+        assert_eq!(packet.received_at, Timestamp(TestConfig::START));
+        assert_eq!(packet.createdate, TestConfig::START);
+    }
+
+    #[test]
+    fn it_replays_idempotent_uploads_for_the_window_only() {
+        let mut test = TestConfig::new();
+        test.config.idempotency_window_secs = 60;
+        let upload = |uuid: &'static str| async move { Ok(Saved { status: "ok", uuid: uuid.into() }) };
+        let send = |uuid| idempotently(&test.config, "/packets", Some("k".to_string()), upload(uuid)).now_or_never().unwrap();
+        let body = |response: HttpResponse| match response.body() {
+            actix_web::dev::ResponseBody::Body(actix_web::dev::Body::Bytes(bytes)) => bytes.clone(),
+            _ => panic!("expected a body"),
+        };
+
+        assert!(body(send("uuid-0")).ends_with(b"\"uuid-0\"}"));
+        test.clock.advance(60 * 1000);
+        assert!(body(send("uuid-1")).ends_with(b"\"uuid-0\"}"));
+        test.clock.advance(1);
+        assert!(body(send("uuid-2")).ends_with(b"\"uuid-2\"}"));
+    }
+
+    #[test]
+    fn it_reads_raw_metadata_from_meta_parameters_and_headers() {
+        let req = actix_web::test::TestRequest::default()
+            .header("X-Tlm-Filetype", "image")
+            .header("X-Tlm-Exposure", "0.5")
+            .header("X-Tlm-Filename", "\"2026\"")
+            .to_http_request();
+        let query = [("meta.filetype", "log"), ("meta.frameCount", "12"), ("meta.camera", "cam2"), ("_", "1"), ("sha256", "ab")];
+        let query = query.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+
+        let metadata = raw_metadata(&req, query).unwrap();
+        assert_eq!(serde_json::Value::Object(metadata), serde_json::json!({
+            "filetype": "image",
+            "filename": "2026",
+            "exposure": 0.5,
+            "frameCount": 12,
+            "camera": "cam2",
+        }));
+    }
+
+    #[test]
+    fn it_streams_a_range_of_a_packet_in_chunks() {
+        let test = TestConfig::new();
+        let conn = database::connection::open(test.config.db.as_path()).unwrap();
+        let bytes = (0..3 * PACKET_CHUNK_SIZE).map(|i| i as u8).collect::<Vec<_>>();
+        let saved = store(&conn, &test.config, &mut Schemas::new(), &upload(&bytes)).unwrap();
+        let id = level_0::get(&conn, &saved.uuid).unwrap().unwrap().id;
+
+        let (start, end) = (1000, 2 * PACKET_CHUNK_SIZE + 10);
+        let chunks = actix_web::rt::System::new("test")
+            .block_on(stream_packet(conn, id, start, end).try_collect::<Vec<_>>())
+            .unwrap();
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks.concat(), &bytes[start..end]);
+    }
+
+    #[test]
+    fn it_parses_batch_field_names() {
+        assert_eq!(parse_indexed_name("packet[3]"), Some(("packet", 3)));
+        assert_eq!(parse_indexed_name("metadata[12]"), Some(("metadata", 12)));
+        assert_eq!(parse_indexed_name("packet"), None);
+        assert_eq!(parse_indexed_name("packet[x]"), None);
+    }
+}
@@ -0,0 +1,140 @@
+use std::error::Error;
+
+use crate::database;
+use crate::database::retention::{self, Expired};
+
+use super::config::Config;
+
+use log::{error, info};
+use rusqlite::TransactionBehavior;
+
+/// What enforcing one retention rule, or emptying the trash, purged.
+#[derive(Debug, PartialEq)]
+pub struct Purged {
+    /// The rule's filetype; `None` for the trash.
+    pub filetype: Option<String>,
+    pub packets: usize,
+    pub bytes: i64,
+}
+
+/// Keeps the background retention task running; it stops when this is dropped.
+pub struct RetentionTask {
+    _timer: timer::Timer,
+    _guard: timer::Guard,
+}
+
+/// Starts enforcing `config.retention`, and purging packets that have been in
+/// the trash longer than `trash_grace_secs`, every `retention_interval_secs`
+/// on a timer thread of its own. An interval of 0 starts nothing.
+pub fn start(config: Config) -> Option<RetentionTask> {
+    if config.retention_interval_secs == 0 {
+        return None;
+    }
+
+    let timer = timer::Timer::new();
+    let interval = chrono::Duration::seconds(config.retention_interval_secs as i64);
+    let guard = timer.schedule_repeating(interval, move || {
+        if let Err(error) = enforce(&config) {
+            error!("failed to enforce retention rules: {:?}", error);
+        }
+    });
+
+    Some(RetentionTask { _timer: timer, _guard: guard })
+}
+
+/// Purges every packet that breaks one of `config.retention`'s rules, then
+/// the trash past its grace period, a batch of `retention_batch_size` at a
+/// time. The trash's tally comes last.
+pub fn enforce(config: &Config) -> Result<Vec<Purged>, Box<dyn Error>> {
+    let mut conn = database::connection::open(config.db.as_path())?;
+    let now = config.clock.now()?;
+    let batch_size = config.retention_batch_size;
+
+    let mut purged = Vec::with_capacity(config.retention.len() + 1);
+    for rule in &config.retention {
+        let filetype = Some(rule.filetype.clone());
+        purged.push(purge_batches(&mut conn, filetype, now, |tx| retention::expired(tx, rule, now, batch_size))?);
+    }
+
+    let deleted_before = now - config.trash_grace_secs as i64 * 1000;
+    purged.push(purge_batches(&mut conn, None, now, |tx| retention::trashed(tx, deleted_before, batch_size))?);
+
+    Ok(purged)
+}
+
+/// Purges the batches `find` turns up until it turns up no more.
+fn purge_batches<F>(conn: &mut rusqlite::Connection, filetype: Option<String>, now: i64, find: F) -> rusqlite::Result<Purged>
+where
+    F: Fn(&rusqlite::Connection) -> rusqlite::Result<Vec<Expired>>,
+{
+    let mut purged = Purged { filetype, packets: 0, bytes: 0 };
+
+    loop {
+        // Small batches keep each write transaction, and so uploads' wait for the write lock, short.
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let batch = find(&tx)?;
+        if batch.is_empty() {
+            break;
+        }
+        retention::purge(&tx, &batch, now)?;
+        tx.commit()?;
+
+        retention::incremental_vacuum(conn)?;
+
+        purged.packets += batch.len();
+        purged.bytes += batch.iter().map(|packet| packet.size).sum::<i64>();
+    }
+
+    if purged.packets > 0 {
+        match &purged.filetype {
+            Some(filetype) => info!("retention purged {} {:?} packets ({} bytes)", purged.packets, filetype, purged.bytes),
+            None => info!("retention purged {} packets from the trash ({} bytes)", purged.packets, purged.bytes),
+        }
+    }
+
+    Ok(purged)
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::params;
+
+    use super::*;
+    use crate::database::retention::RetentionRule;
+    use crate::testing::TestConfig;
+
+    #[test]
+    fn it_purges_at_the_configured_cut_offs() {
+        let mut test = TestConfig::new();
+        test.config.trash_grace_secs = 10;
+        test.config.retention = vec![RetentionRule {
+            filetype: "log".into(),
+            max_age_secs: Some(60),
+            max_total_bytes: None,
+            keep_last: None,
+        }];
+
+        // Received a second apart, the first of them trashed straight away.
+        let conn = database::connection::open(test.config.db.as_path()).unwrap();
+        for i in 0..3 {
+            let at = TestConfig::START + i * 1000;
+            conn.execute(
+                "insert into level_0 (uuid, createdate, received_at, metadata, filetype, packet, deleted_at)
+                values (?1, ?2, ?2, '{}', 'log', x'00', ?3)",
+                params![format!("uuid-{}", i), at, if i == 0 { Some(at) } else { None }],
+            )
+            .unwrap();
+        }
+        let counts = |purged: Vec<Purged>| purged.iter().map(|purged| purged.packets).collect::<Vec<_>>();
+
+        test.clock.advance(10 * 1000);
+        assert_eq!(counts(enforce(&test.config).unwrap()), [0, 0]);
+        test.clock.advance(1);
+        assert_eq!(counts(enforce(&test.config).unwrap()), [0, 1]);
+
+        test.clock.set(TestConfig::START + 1000 + 60 * 1000);
+        assert_eq!(counts(enforce(&test.config).unwrap()), [0, 0]);
+        test.clock.advance(1);
+        assert_eq!(counts(enforce(&test.config).unwrap()), [1, 0]);
+    }
+}
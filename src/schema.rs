@@ -0,0 +1,118 @@
+use crate::database;
+use crate::database::metadata_schema::{self, MetadataSchema};
+
+use super::config::Config;
+
+use actix_web::{web, HttpResponse};
+use jsonschema::JSONSchema;
+use serde_json::Value;
+
+/// Lists the JSON Schemas registered for packet metadata.
+pub async fn list(config: web::Data<Config>) -> HttpResponse {
+    let result = database::connection::open(config.db.as_path())
+        .and_then(|conn| metadata_schema::list(&conn));
+
+    match result {
+        Ok(schemas) => HttpResponse::Ok().json(schemas),
+        Err(error) => HttpResponse::InternalServerError().body(format!("{:?}", error)),
+    }
+}
+
+/// Fetches the JSON Schema registered for `filetype`, or 404s if there is none.
+pub async fn fetch(config: web::Data<Config>, filetype: String) -> HttpResponse {
+    let result = database::connection::open(config.db.as_path())
+        .and_then(|conn| metadata_schema::get(&conn, &filetype));
+
+    match result {
+        Ok(Some(schema)) => HttpResponse::Ok().json(schema),
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(error) => HttpResponse::InternalServerError().body(format!("{:?}", error)),
+    }
+}
+
+/// Registers `schema` as the JSON Schema the metadata of every packet of
+/// `filetype` must match from now on. Packets already stored aren't rechecked.
+pub async fn register(config: web::Data<Config>, filetype: String, schema: Value) -> HttpResponse {
+    // Refuse a schema that can't be compiled now, rather than failing every upload later.
+    if let Err(error) = JSONSchema::compile(&schema) {
+        return HttpResponse::BadRequest().body(format!("invalid JSON Schema: {}", error));
+    }
+
+    let result = config.clock.now().map_err(|error| format!("{:?}", error)).and_then(|now| {
+        database::connection::open(config.db.as_path())
+            .and_then(|conn| {
+                metadata_schema::put(&conn, &filetype, &schema, now)?;
+                Ok(MetadataSchema { filetype, createdate: now, schema })
+            })
+            .map_err(|error| format!("{:?}", error))
+    });
+
+    match result {
+        Ok(schema) => HttpResponse::Ok().json(schema),
+        Err(error) => HttpResponse::InternalServerError().body(error),
+    }
+}
+
+/// Drops the JSON Schema for `filetype`, or 404s if there is none.
+pub async fn remove(config: web::Data<Config>, filetype: String) -> HttpResponse {
+    let result = database::connection::open(config.db.as_path())
+        .and_then(|conn| metadata_schema::delete(&conn, &filetype));
+
+    match result {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => HttpResponse::NotFound().finish(),
+        Err(error) => HttpResponse::InternalServerError().body(format!("{:?}", error)),
+    }
+}
+
+/// Compiles `schema` for `validate`. Errors if it isn't a JSON Schema that
+/// can be compiled.
+pub fn compile(schema: &Value) -> Result<JSONSchema, String> {
+    JSONSchema::compile(schema).map_err(|error| format!("invalid JSON Schema: {}", error))
+}
+
+/// Checks `metadata` against `schema`, returning every violation found (none
+/// if it matches), each prefixed with the JSON Pointer of the offending value.
+pub fn validate(schema: &JSONSchema, metadata: &Value) -> Vec<String> {
+    match schema.validate(metadata) {
+        Ok(()) => Vec::new(),
+        Err(errors) => errors
+            .map(|error| match error.instance_path.to_string() {
+                path if path.is_empty() => error.to_string(),
+                path => format!("{}: {}", path, error),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn it_lists_every_violation() {
+        let schema = json!({
+            "type": "object",
+            "required": ["filename", "filetype", "width"],
+            "properties": {
+                "width": {"type": "integer"},
+                "height": {"type": "integer", "minimum": 1},
+            },
+        });
+
+        let schema = compile(&schema).unwrap();
+
+        let ok = json!({"filename": "a.png", "filetype": "image", "width": 2, "height": 3});
+        assert!(validate(&schema, &ok).is_empty());
+
+        let bad = json!({"filename": "a.png", "filetype": "image", "height": 0});
+        let violations = validate(&schema, &bad);
+        assert_eq!(violations.len(), 2);
+        assert!(violations.iter().any(|violation| violation.contains("\"width\" is a required property")));
+        assert!(violations.iter().any(|violation| violation.starts_with("/height: ")));
+
+        assert!(compile(&json!({"type": "no-such-type"})).is_err());
+    }
+}
@@ -1,8 +1,12 @@
 use crate::config::Config;
 
 // use crate::handlers::health::get_health;
-// use crate::handlers::packet::post;
-// use crate::handlers::packet::get_all;
+use crate::handlers::packet::{get_all, get_one, get_search, delete_one, restore_one, patch_metadata, get_metadata_history, post_packet, post_batch, post_raw};
+use crate::handlers::schema::{get_schemas, get_schema, put_schema, delete_schema};
+use crate::handlers::index::{get_indexes, put_index};
+use crate::handlers::hold::{get_hold, put_hold, delete_hold};
+use crate::handlers::tag::{post_tags, delete_tags};
+use crate::handlers::value::{get_values, get_value, put_value, delete_value, get_value_history, post_increment, get_watch};
 use std::error::Error;
 // use actix_cors::Cors;
 // use actix_session::CookieSession;
@@ -31,18 +35,67 @@ fn setup_routes(cfg: &mut ServiceConfig) {
     cfg
 
         // Healthcheck
-        .route("/health", web::get().to(|| HttpResponse::Ok()))
+        .route("/health", web::get().to(HttpResponse::Ok))
 
             // Raw Packet Routes
+            // (`/{id}` has to live inside the scope: a scope claims every path
+            // under its prefix, so a sibling `/packets/{id}` resource never matches.)
             .service(
                 web::scope("/packets")
-                    .route("", web::get().to(|| HttpResponse::Ok()))
-                    .route("", web::post().to(|| HttpResponse::Ok(packet::))),
+                    .route("", web::get().to(get_all))
+                    .route("", web::post().to(post_packet))
+                    .route("/batch", web::post().to(post_batch))
+                    .route("/raw", web::post().to(post_raw))
+                    .route("/search", web::get().to(get_search))
+                    .service(
+                        web::resource("/{id}")
+                            .route(web::get().to(get_one))
+                            .route(web::delete().to(delete_one)),
+                    )
+                    .route("/{id}/restore", web::post().to(restore_one))
+                    .route("/{id}/metadata", web::patch().to(patch_metadata))
+                    .route("/{id}/metadata/history", web::get().to(get_metadata_history))
+                    .service(
+                        web::resource("/{id}/tags")
+                            .route(web::post().to(post_tags))
+                            .route(web::delete().to(delete_tags)),
+                    ),
             )
+
+            // Single Value Routes
+            .service(
+                web::scope("/values")
+                    .route("", web::get().to(get_values))
+                    // (Ahead of `/{name}`, which would otherwise take GETs of it; no value may be named `watch`.)
+                    .route("/watch", web::get().to(get_watch))
+                    .service(
+                        web::resource("/{name}")
+                            .route(web::get().to(get_value))
+                            .route(web::put().to(put_value))
+                            .route(web::delete().to(delete_value)),
+                    )
+                    .route("/{name}/history", web::get().to(get_value_history))
+                    .route("/{name}/increment", web::post().to(post_increment)),
+            )
+
+            // Admin Routes
             .service(
-                web::resource("/packets/{id}")
-                    .route(web::get().to(|| HttpResponse::Ok()))
-                    .route(web::delete().to(|| HttpResponse::Ok())),
+                web::scope("/admin")
+                    .route("/schemas", web::get().to(get_schemas))
+                    .service(
+                        web::resource("/schemas/{filetype}")
+                            .route(web::get().to(get_schema))
+                            .route(web::put().to(put_schema))
+                            .route(web::delete().to(delete_schema)),
+                    )
+                    .route("/indexes", web::get().to(get_indexes))
+                    .route("/indexes/{path}", web::put().to(put_index))
+                    .service(
+                        web::resource("/packets/{id}/hold")
+                            .route(web::get().to(get_hold))
+                            .route(web::put().to(put_hold))
+                            .route(web::delete().to(delete_hold)),
+                    ),
             );
 
             // .default_service(web::route().to(|| HttpResponse::NotFound().body("404")
@@ -0,0 +1,98 @@
+use crate::database;
+use crate::database::tag;
+
+use super::config::Config;
+
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+/// The tags to put on, or take off, a packet.
+#[derive(Deserialize, Debug)]
+pub struct TagRequest {
+    pub tags: Vec<String>,
+}
+
+/// The tags to take off a packet, as `?tags=anomaly,pass-2026-10-17`.
+#[derive(Deserialize, Debug)]
+pub struct TagQuery {
+    pub tags: String,
+}
+
+impl From<TagQuery> for TagRequest {
+    fn from(query: TagQuery) -> Self {
+        let tags = query.tags.split(',').map(str::trim).filter(|name| !name.is_empty()).map(str::to_string);
+        TagRequest { tags: tags.collect() }
+    }
+}
+
+/// A packet's tags after a change.
+#[derive(Serialize, Debug)]
+struct Tags {
+    tags: Vec<String>,
+}
+
+/// Tags packet `uuid`, e.g. `anomaly` or `pass-2026-10-17`. Tags it already
+/// has are left be. Packets under legal hold can still be tagged; tags are
+/// for curating packets, not part of them.
+pub async fn add(config: web::Data<Config>, uuid: String, request: TagRequest) -> HttpResponse {
+    if let Some(response) = check(&request) {
+        return response;
+    }
+
+    let result = config.clock.now().map_err(|error| format!("{:?}", error)).and_then(|now| {
+        database::connection::open(config.db.as_path())
+            .and_then(|conn| tag::add(&conn, &uuid, &request.tags, now))
+            .map_err(|error| format!("{:?}", error))
+    });
+
+    respond(result)
+}
+
+/// Takes tags off packet `uuid`. Tags it doesn't have are ignored.
+///
+/// The tags come from `?tags=`, since clients and proxies are apt to drop
+/// the body of a `DELETE`.
+pub async fn remove(config: web::Data<Config>, uuid: String, request: TagRequest) -> HttpResponse {
+    if let Some(response) = check(&request) {
+        return response;
+    }
+
+    let result = database::connection::open(config.db.as_path())
+        .and_then(|conn| tag::remove(&conn, &uuid, &request.tags))
+        .map_err(|error| format!("{:?}", error));
+
+    respond(result)
+}
+
+fn check(request: &TagRequest) -> Option<HttpResponse> {
+    if request.tags.is_empty() {
+        return Some(HttpResponse::BadRequest().body("tags must not be empty"));
+    }
+    request.tags.iter().find(|name| !tag::is_tag(name)).map(|name| {
+        HttpResponse::BadRequest().body(format!(
+            "{:?} is not a tag: use 1 to {} letters, digits and -_.:",
+            name,
+            tag::MAX_TAG_LEN,
+        ))
+    })
+}
+
+fn respond(result: Result<Option<Vec<String>>, String>) -> HttpResponse {
+    match result {
+        Ok(Some(tags)) => HttpResponse::Ok().json(Tags { tags }),
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(error) => HttpResponse::InternalServerError().body(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_splits_tags_from_the_query() {
+        let request = TagRequest::from(TagQuery { tags: "anomaly, pass-2026-10-17,,".to_string() });
+        assert_eq!(request.tags, ["anomaly", "pass-2026-10-17"]);
+        assert!(TagRequest::from(TagQuery { tags: String::new() }).tags.is_empty());
+    }
+}
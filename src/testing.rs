@@ -0,0 +1,48 @@
+//! Helpers for tests that need a `Config` with a db on disk behind it.
+
+use std::path::PathBuf;
+
+use uuid::Uuid;
+
+use crate::clock::{ManualClock, SharedClock};
+use crate::config::Config;
+use crate::database;
+
+/// A `Config` whose db, with every migration applied, and temp dir are in a
+/// directory of their own, which is removed again when this is dropped.
+///
+/// Its clock is a `ManualClock`, stopped at `START` until the test moves it.
+pub struct TestConfig {
+    pub config: Config,
+    pub clock: ManualClock,
+    dir: PathBuf,
+}
+
+impl TestConfig {
+    /// Where the clock starts, in ms since the epoch.
+    pub const START: i64 = 1_000_000;
+
+    pub fn new() -> Self {
+        let clock = ManualClock::new(Self::START);
+        let dir = std::env::temp_dir().join(format!("tlm-test-{}", Uuid::new_v4()));
+        let config = Config {
+            db: dir.join("tlm.db"),
+            test_db: dir.join("test.db"),
+            file_tmp_path: dir.join("temp"),
+            clock: SharedClock::new(clock.clone()),
+            ..Config::default()
+        };
+        std::fs::create_dir_all(&config.file_tmp_path).unwrap();
+
+        let conn = database::connection::open(config.db.as_path()).unwrap();
+        database::migrations::apply_all(&conn).unwrap();
+
+        TestConfig { config, clock, dir }
+    }
+}
+
+impl Drop for TestConfig {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
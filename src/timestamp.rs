@@ -0,0 +1,101 @@
+use std::convert::TryFrom;
+use std::fmt;
+
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use rusqlite::types::{FromSql, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+
+/// A point in time, to the millisecond, stored as ms since the Unix epoch.
+///
+/// It is rendered as ISO-8601 (`2024-05-01T12:00:00.250Z`) in JSON, and read
+/// from either ISO-8601 or a number of ms since the epoch, so clients can
+/// send whichever they have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Timestamp(pub i64);
+
+impl Timestamp {
+    /// Parses ms since the epoch, or an ISO-8601 (RFC 3339) date-time with an offset.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        if let Ok(millis) = value.parse::<i64>() {
+            return Ok(Timestamp(millis));
+        }
+        DateTime::parse_from_rfc3339(value)
+            .map(|datetime| Timestamp(datetime.timestamp_millis()))
+            .map_err(|_| format!("{:?} is neither ms since the epoch nor an ISO-8601 date-time", value))
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match Utc.timestamp_millis_opt(self.0).single() {
+            Some(datetime) => f.write_str(&datetime.to_rfc3339_opts(SecondsFormat::Millis, true)),
+            // Out of chrono's range; the raw number is better than nothing.
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TimestampVisitor;
+
+        impl<'de> Visitor<'de> for TimestampVisitor {
+            type Value = Timestamp;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("ms since the epoch or an ISO-8601 date-time")
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<Timestamp, E> {
+                Ok(Timestamp(value))
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<Timestamp, E> {
+                i64::try_from(value).map(Timestamp).map_err(E::custom)
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Timestamp, E> {
+                Timestamp::parse(value).map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(TimestampVisitor)
+    }
+}
+
+impl ToSql for Timestamp {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        self.0.to_sql()
+    }
+}
+
+impl FromSql for Timestamp {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        i64::column_result(value).map(Timestamp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_reads_ms_or_iso_8601() {
+        assert_eq!(Timestamp::parse("1714564800250"), Ok(Timestamp(1714564800250)));
+        assert_eq!(Timestamp::parse("2024-05-01T12:00:00.250Z"), Ok(Timestamp(1714564800250)));
+        assert_eq!(Timestamp::parse("2024-05-01T14:00:00.250+02:00"), Ok(Timestamp(1714564800250)));
+        assert!(Timestamp::parse("yesterday").is_err());
+
+        let from_json: Timestamp = serde_json::from_str("1714564800250").unwrap();
+        assert_eq!(from_json, Timestamp(1714564800250));
+        assert_eq!(serde_json::to_string(&from_json).unwrap(), r#""2024-05-01T12:00:00.250Z""#);
+    }
+}
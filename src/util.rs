@@ -2,7 +2,6 @@ use std::convert::TryInto;
 use std::error::Error;
 use std::fs::File;
 use std::io::Read;
-use std::io::Write;
 use std::path::Path;
 use std::string::*;
 use std::time::SystemTime;
@@ -15,16 +14,11 @@ pub fn load_string(file_name: &str) -> Result<String, Box<dyn Error>> {
   Ok(result)
 }
 
-pub fn write_string(file_name: &str, text: &str) -> Result<usize, Box<dyn Error>> {
-  let path = &Path::new(&file_name);
-  let mut outf = File::create(path)?;
-  Ok(outf.write(text.as_bytes())?)
-}
-
+/// The current time, in ms since the Unix epoch.
 pub fn now() -> Result<i64, Box<dyn Error>> {
-  let nowsecs = SystemTime::now()
+  let nowmillis = SystemTime::now()
     .duration_since(SystemTime::UNIX_EPOCH)
-    .map(|n| n.as_secs())?;
-  let s: i64 = nowsecs.try_into()?;
-  Ok(s * 1000)
+    .map(|n| n.as_millis())?;
+  let ms: i64 = nowmillis.try_into()?;
+  Ok(ms)
 }
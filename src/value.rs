@@ -0,0 +1,585 @@
+use crate::database;
+use crate::database::single_value::{self, Change, HistoryQuery, SingleValue, TypedValue, ValueType};
+use crate::notifier::ChangeNotifier;
+use crate::timestamp::Timestamp;
+
+use super::config::Config;
+
+use std::time::Duration;
+use actix_web::{error, error::BlockingError, http::header, rt::time::delay_for, web, HttpRequest, HttpResponse};
+use futures_util::future::{select, Either};
+use futures_util::{stream, Stream};
+use rusqlite::{Connection, TransactionBehavior};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Longest name, or namespace, a value can be stored under.
+const MAX_NAME_LEN: usize = 255;
+
+/// How long a long-poll watch waits for a change by default, and at most, in seconds.
+const DEFAULT_WATCH_TIMEOUT_SECS: u64 = 30;
+const MAX_WATCH_TIMEOUT_SECS: u64 = 120;
+
+/// Most changes handed out at once by a watch.
+const MAX_WATCH_CHANGES: u32 = 1000;
+
+/// How often an idle event stream sends a comment, so proxies don't drop it.
+const EVENT_STREAM_KEEPALIVE_SECS: u64 = 15;
+
+/// The name under `/values` that watches them, which no value can have.
+const WATCH: &str = "watch";
+
+/// `Last-Event-ID` isn't among actix's known headers.
+const LAST_EVENT_ID: &str = "last-event-id";
+
+/// The namespace a request is about, from `?namespace=`, e.g. a spacecraft
+/// or ground station. Leaving it out means the default namespace, `""`.
+#[derive(Deserialize, Debug)]
+pub struct Namespace {
+    #[serde(default)]
+    pub namespace: String,
+}
+
+/// Which values to list.
+#[derive(Deserialize, Debug)]
+pub struct ValueQuery {
+    /// Only values whose names start with this, e.g. `sc1.`.
+    pub prefix: Option<String>,
+}
+
+/// A value to store, e.g. `{"type": "float", "value": 7.9}`. Values without a
+/// type are strings.
+///
+/// It can be given an `expires_at` time, or a `ttl_secs` from now, after which
+/// it reads as missing.
+#[derive(Deserialize, Debug)]
+pub struct ValueRequest {
+    #[serde(rename = "type", default)]
+    pub value_type: ValueType,
+    pub value: Value,
+    pub expires_at: Option<Timestamp>,
+    pub ttl_secs: Option<i64>,
+}
+
+/// Which changes to watch for.
+#[derive(Deserialize, Debug)]
+pub struct WatchQuery {
+    /// Only values whose names start with this, e.g. `sc1.`.
+    pub prefix: Option<String>,
+    /// The `seq` of the last change seen. Without one, only changes from now
+    /// on are watched for.
+    pub since: Option<i64>,
+    /// How long a long-poll waits for a change, in seconds.
+    pub timeout: Option<u64>,
+}
+
+/// The answer to a long-poll watch.
+#[derive(Serialize, Debug)]
+struct Watched {
+    changes: Vec<Change>,
+    /// Watch `since` this next.
+    next: i64,
+}
+
+/// How much to add to a counter.
+#[derive(Deserialize, Debug)]
+pub struct IncrementQuery {
+    /// Defaults to 1; may be negative.
+    pub by: Option<i64>,
+}
+
+/// Why a write was turned down.
+#[derive(Debug, PartialEq)]
+enum Refusal {
+    /// `If-Match` or `If-None-Match` didn't hold.
+    PreconditionFailed,
+    /// Only `int` values can be incremented.
+    NotAnInt,
+    Overflow,
+    /// The value would have expired before it was written.
+    AlreadyExpired,
+}
+
+/// Lists the values in `namespace` that haven't expired, by name.
+pub async fn list(config: web::Data<Config>, namespace: String, query: ValueQuery) -> HttpResponse {
+    let result = config.clock.now().map_err(|error| format!("{:?}", error)).and_then(|now| {
+        database::connection::open(config.db.as_path())
+            .and_then(|conn| single_value::list(&conn, &namespace, query.prefix.as_deref().unwrap_or(""), now))
+            .map_err(|error| format!("{:?}", error))
+    });
+
+    match result {
+        Ok(values) => HttpResponse::Ok().json(values),
+        Err(error) => HttpResponse::InternalServerError().body(error),
+    }
+}
+
+/// Fetches the value stored under `name` in `namespace`, or 404s if there is
+/// none or it has expired.
+pub async fn fetch(config: web::Data<Config>, namespace: String, name: String) -> HttpResponse {
+    let result = config.clock.now().map_err(|error| format!("{:?}", error)).and_then(|now| {
+        database::connection::open(config.db.as_path())
+            .and_then(|conn| single_value::get(&conn, &namespace, &name, now))
+            .map_err(|error| format!("{:?}", error))
+    });
+
+    match result {
+        Ok(Some(value)) => HttpResponse::Ok().header(header::ETAG, etag(&value)).json(value),
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(error) => HttpResponse::InternalServerError().body(error),
+    }
+}
+
+/// Stores a value under `name` in `namespace`, replacing whatever was there,
+/// once it has been checked against its type. 400s if it isn't one, or if it
+/// would already have expired.
+///
+/// Honours `If-Match` with the `ETag` of the revision the client last saw, so
+/// writers don't clobber each other, and `If-None-Match: *` to only create
+/// the value. Either failing is a 412.
+pub async fn store(req: HttpRequest, config: web::Data<Config>, namespace: String, name: String, request: ValueRequest) -> HttpResponse {
+    if let Some(response) = check_key(&namespace, &name) {
+        return response;
+    }
+    if request.expires_at.is_some() && request.ttl_secs.is_some() {
+        return HttpResponse::BadRequest().body("give expires_at or ttl_secs, not both");
+    }
+    let value = match TypedValue::new(request.value_type, &request.value) {
+        Ok(value) => value,
+        Err(error) => return HttpResponse::BadRequest().body(error),
+    };
+
+    respond(write(&config, |conn, now| {
+        let expires_at = match (request.expires_at, request.ttl_secs) {
+            (Some(expires_at), _) => Some(expires_at.0),
+            (None, Some(ttl_secs)) => Some(now.saturating_add(ttl_secs.saturating_mul(1000))),
+            (None, None) => None,
+        };
+        if expires_at.is_some_and(|expires_at| expires_at <= now) {
+            return Ok(Err(Refusal::AlreadyExpired));
+        }
+
+        let current = single_value::get(conn, &namespace, &name, now)?;
+        if !preconditions_hold(&req, current.as_ref()) {
+            return Ok(Err(Refusal::PreconditionFailed));
+        }
+        let revision = single_value::set(conn, &namespace, &name, &value, expires_at, now)?;
+        Ok(Ok(SingleValue {
+            namespace,
+            name,
+            value,
+            updated_at: Some(Timestamp(now)),
+            revision,
+            expires_at: expires_at.map(Timestamp),
+        }))
+    }))
+}
+
+/// Watches the values in `namespace` whose names start with `prefix` for
+/// writes after `since`, a `seq` from an earlier change.
+///
+/// As a long-poll, it answers as soon as there are changes, or with none once
+/// `timeout` is up. Clients that accept `text/event-stream` get the changes as
+/// server-sent events instead, each with its `seq` as the event id, so a
+/// reconnecting client's `Last-Event-ID` carries on where it left off.
+///
+/// Deleting a value shows up as a change of type `deleted`, and so does its
+/// expiring, once it is swept or written again.
+pub async fn watch(req: HttpRequest, config: web::Data<Config>, namespace: String, query: WatchQuery) -> HttpResponse {
+    let last_event_id = req
+        .headers()
+        .get(LAST_EVENT_ID)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok());
+    let since = query.since.or(last_event_id);
+    let prefix = query.prefix.unwrap_or_default();
+
+    let db = config.db.clone();
+    let opened = web::block(move || {
+        let conn = database::connection::open(db.as_path())?;
+        let since = match since {
+            Some(since) => since,
+            None => single_value::latest_seq(&conn)?,
+        };
+        Ok::<_, rusqlite::Error>((conn, since))
+    })
+    .await;
+    let (conn, since) = match opened {
+        Ok(opened) => opened,
+        Err(error) => return HttpResponse::InternalServerError().body(format!("{:?}", error)),
+    };
+
+    let notifier = config.value_changes.clone();
+    let accepts_events = req
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/event-stream"));
+    if accepts_events {
+        return HttpResponse::Ok()
+            .content_type("text/event-stream")
+            .header(header::CACHE_CONTROL, "no-cache")
+            .streaming(stream_changes(conn, notifier, namespace, prefix, since));
+    }
+
+    let timeout = query.timeout.unwrap_or(DEFAULT_WATCH_TIMEOUT_SECS).min(MAX_WATCH_TIMEOUT_SECS);
+    match long_poll(conn, &notifier, &namespace, &prefix, since, Duration::from_secs(timeout)).await {
+        Ok(watched) => HttpResponse::Ok().json(watched),
+        Err(error) => HttpResponse::InternalServerError().body(format!("{:?}", error)),
+    }
+}
+
+/// Waits up to `timeout` for writes after `since`.
+async fn long_poll(
+    mut conn: Connection,
+    notifier: &ChangeNotifier,
+    namespace: &str,
+    prefix: &str,
+    since: i64,
+    timeout: Duration,
+) -> Result<Watched, BlockingError<rusqlite::Error>> {
+    let mut deadline = Box::pin(delay_for(timeout));
+    loop {
+        // Taken before looking, so a write landing in between still wakes us.
+        let generation = notifier.generation();
+        let (returned, changes) = changes_after(conn, namespace.to_string(), prefix.to_string(), since).await?;
+        conn = returned;
+        if let Some(last) = changes.last() {
+            return Ok(Watched { next: last.seq, changes });
+        }
+
+        match select(notifier.changed(generation), deadline).await {
+            Either::Left(((), remaining)) => deadline = remaining,
+            Either::Right(_) => return Ok(Watched { changes, next: since }),
+        }
+    }
+}
+
+/// Streams writes after `since` as server-sent events as they happen, with a
+/// comment every so often while there are none.
+fn stream_changes(
+    conn: Connection,
+    notifier: ChangeNotifier,
+    namespace: String,
+    prefix: String,
+    since: i64,
+) -> impl Stream<Item = Result<web::Bytes, actix_web::Error>> + Unpin {
+    Box::pin(stream::unfold(Some((conn, since)), move |state| {
+        let notifier = notifier.clone();
+        let namespace = namespace.clone();
+        let prefix = prefix.clone();
+        async move {
+            let (mut conn, since) = state?;
+            loop {
+                let generation = notifier.generation();
+                let changes = match changes_after(conn, namespace.clone(), prefix.clone(), since).await {
+                    Ok((returned, changes)) => {
+                        conn = returned;
+                        changes
+                    }
+                    // Surface the error once, then end the stream.
+                    Err(e) => return Some((Err(error::ErrorInternalServerError(e)), None)),
+                };
+                if let Some(last) = changes.last() {
+                    let next = last.seq;
+                    return match events(&changes) {
+                        Ok(events) => Some((Ok(web::Bytes::from(events)), Some((conn, next)))),
+                        Err(e) => Some((Err(error::ErrorInternalServerError(e)), None)),
+                    };
+                }
+
+                let keepalive = Box::pin(delay_for(Duration::from_secs(EVENT_STREAM_KEEPALIVE_SECS)));
+                if let Either::Right(_) = select(notifier.changed(generation), keepalive).await {
+                    return Some((Ok(web::Bytes::from_static(b": keepalive\n\n")), Some((conn, since))));
+                }
+            }
+        }
+    }))
+}
+
+/// Up to `MAX_WATCH_CHANGES` writes after `since`, looked up on the blocking
+/// thread pool, which `conn` is handed to and back from.
+async fn changes_after(
+    conn: Connection,
+    namespace: String,
+    prefix: String,
+    since: i64,
+) -> Result<(Connection, Vec<Change>), BlockingError<rusqlite::Error>> {
+    web::block(move || {
+        let changes = single_value::changes(&conn, &namespace, &prefix, since, MAX_WATCH_CHANGES)?;
+        Ok((conn, changes))
+    })
+    .await
+}
+
+/// `changes` as server-sent events.
+fn events(changes: &[Change]) -> serde_json::Result<String> {
+    let mut events = String::new();
+    for change in changes {
+        events.push_str(&format!("id: {}\ndata: {}\n\n", change.seq, serde_json::to_string(change)?));
+    }
+    Ok(events)
+}
+
+/// Adds `by` to the `int` value under `name` in `namespace` in one step, so
+/// concurrent counters don't lose updates. A missing (or expired) value
+/// counts from 0; otherwise it keeps its expiry. 409s if the value isn't an
+/// `int`, or would overflow.
+pub async fn increment(config: web::Data<Config>, namespace: String, name: String, query: IncrementQuery) -> HttpResponse {
+    if let Some(response) = check_key(&namespace, &name) {
+        return response;
+    }
+
+    let by = query.by.unwrap_or(1);
+    respond(write(&config, |conn, now| add(conn, &namespace, name, by, now)))
+}
+
+/// Adds `by` to the `int` value under `name` in `namespace` at `now`, on
+/// `conn`, which the caller holds a write transaction on.
+fn add(conn: &Connection, namespace: &str, name: String, by: i64, now: i64) -> rusqlite::Result<Result<SingleValue, Refusal>> {
+    let (count, expires_at) = match single_value::get(conn, namespace, &name, now)? {
+        None => (Some(0), None),
+        Some(current) => (current.value.as_i64(), current.expires_at.map(|expires_at| expires_at.0)),
+    };
+    let count = match count {
+        Some(count) => count.checked_add(by),
+        None => return Ok(Err(Refusal::NotAnInt)),
+    };
+    let value = match count {
+        Some(count) => TypedValue::new(ValueType::Int, &Value::from(count)).expect("an i64 is an int"),
+        None => return Ok(Err(Refusal::Overflow)),
+    };
+    let revision = single_value::set(conn, namespace, &name, &value, expires_at, now)?;
+    Ok(Ok(SingleValue {
+        namespace: namespace.to_string(),
+        name,
+        value,
+        updated_at: Some(Timestamp(now)),
+        revision,
+        expires_at: expires_at.map(Timestamp),
+    }))
+}
+
+fn check_key(namespace: &str, name: &str) -> Option<HttpResponse> {
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        return Some(HttpResponse::BadRequest().body(format!("names must be 1 to {} characters", MAX_NAME_LEN)));
+    }
+    // `/values/watch` is the watch endpoint, so a value by that name couldn't be read back.
+    if name == WATCH {
+        return Some(HttpResponse::BadRequest().body(format!("{:?} is reserved", WATCH)));
+    }
+    if namespace.len() > MAX_NAME_LEN {
+        return Some(HttpResponse::BadRequest().body(format!("namespaces must be at most {} characters", MAX_NAME_LEN)));
+    }
+    None
+}
+
+/// Runs `change` in a write transaction at the current time, committing it
+/// unless the write was refused.
+fn write<F>(config: &Config, change: F) -> Result<Result<SingleValue, Refusal>, String>
+where
+    F: FnOnce(&Connection, i64) -> rusqlite::Result<Result<SingleValue, Refusal>>,
+{
+    let now = config.clock.now().map_err(|error| format!("{:?}", error))?;
+    database::connection::open(config.db.as_path())
+        .and_then(|mut conn| {
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            let written = change(&tx, now)?;
+            if written.is_ok() {
+                tx.commit()?;
+                config.value_changes.notify();
+            }
+            Ok(written)
+        })
+        .map_err(|error| format!("{:?}", error))
+}
+
+fn respond(result: Result<Result<SingleValue, Refusal>, String>) -> HttpResponse {
+    match result {
+        Ok(Ok(value)) => HttpResponse::Ok().header(header::ETAG, etag(&value)).json(value),
+        Ok(Err(Refusal::PreconditionFailed)) => HttpResponse::PreconditionFailed().body("value has changed"),
+        Ok(Err(Refusal::NotAnInt)) => HttpResponse::Conflict().body("only int values can be incremented"),
+        Ok(Err(Refusal::Overflow)) => HttpResponse::Conflict().body("increment would overflow"),
+        Ok(Err(Refusal::AlreadyExpired)) => HttpResponse::BadRequest().body("the value would already have expired"),
+        Err(error) => HttpResponse::InternalServerError().body(error),
+    }
+}
+
+/// A value's revision makes its `ETag`.
+fn etag(value: &SingleValue) -> String {
+    format!("\"{}\"", value.revision)
+}
+
+/// Whether the `If-Match` and `If-None-Match` headers of `req`, if it has
+/// them, allow replacing `current`.
+fn preconditions_hold(req: &HttpRequest, current: Option<&SingleValue>) -> bool {
+    let etag = current.map(etag);
+    let matches = |header_value: &str| match &etag {
+        Some(etag) => header_value.trim() == "*" || header_value.split(',').any(|candidate| candidate.trim() == etag),
+        None => false,
+    };
+
+    let header = |name| req.headers().get(name).and_then(|value| value.to_str().ok());
+    if let Some(if_match) = header(header::IF_MATCH) {
+        if !matches(if_match) {
+            return false;
+        }
+    }
+    if let Some(if_none_match) = header(header::IF_NONE_MATCH) {
+        if matches(if_none_match) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Drops the value stored under `name` in `namespace`, or 404s if there is
+/// none or it has expired.
+pub async fn remove(config: web::Data<Config>, namespace: String, name: String) -> HttpResponse {
+    let result = config.clock.now().map_err(|error| format!("{:?}", error)).and_then(|now| {
+        database::connection::open(config.db.as_path())
+            .and_then(|mut conn| {
+                let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+                let deleted = single_value::delete(&tx, &namespace, &name, now)?;
+                tx.commit()?;
+                Ok(deleted)
+            })
+            .map_err(|error| format!("{:?}", error))
+    });
+
+    match result {
+        Ok(true) => {
+            config.value_changes.notify();
+            HttpResponse::NoContent().finish()
+        }
+        Ok(false) => HttpResponse::NotFound().finish(),
+        Err(error) => HttpResponse::InternalServerError().body(error),
+    }
+}
+
+/// Lists the writes of the value under `name` in `namespace` between `from`
+/// and `to`, oldest first, so it can be plotted over time. Writes are kept
+/// after the value is deleted or expires, which is itself a write of type
+/// `deleted`.
+pub async fn history(config: web::Data<Config>, namespace: String, name: String, query: HistoryQuery) -> HttpResponse {
+    let result = database::connection::open(config.db.as_path())
+        .and_then(|conn| single_value::history(&conn, &namespace, &name, &query));
+
+    match result {
+        Ok(writes) => HttpResponse::Ok().json(writes),
+        Err(error) => HttpResponse::InternalServerError().body(format!("{:?}", error)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test::TestRequest;
+    use futures_util::FutureExt;
+    use serde_json::json;
+
+    use super::*;
+    use crate::database::migrations;
+    use crate::testing::TestConfig;
+
+    #[test]
+    fn it_checks_if_match_and_if_none_match() {
+        let current = SingleValue {
+            namespace: String::new(),
+            name: "sc1.mode".into(),
+            value: TypedValue::new(ValueType::String, &json!("safe")).unwrap(),
+            updated_at: None,
+            revision: 2,
+            expires_at: None,
+        };
+        let holds = |name, value: &str, current: Option<&SingleValue>| {
+            preconditions_hold(&TestRequest::default().header(name, value).to_http_request(), current)
+        };
+
+        assert!(preconditions_hold(&TestRequest::default().to_http_request(), Some(&current)));
+        assert!(holds(header::IF_MATCH, "\"2\"", Some(&current)));
+        assert!(holds(header::IF_MATCH, "\"1\", \"2\"", Some(&current)));
+        assert!(holds(header::IF_MATCH, "*", Some(&current)));
+        assert!(!holds(header::IF_MATCH, "\"1\"", Some(&current)));
+        assert!(!holds(header::IF_MATCH, "\"2\"", None));
+        assert!(!holds(header::IF_MATCH, "*", None));
+
+        assert!(!holds(header::IF_NONE_MATCH, "*", Some(&current)));
+        assert!(!holds(header::IF_NONE_MATCH, "\"3\", \"2\"", Some(&current)));
+        assert!(holds(header::IF_NONE_MATCH, "\"1\"", Some(&current)));
+        assert!(holds(header::IF_NONE_MATCH, "*", None));
+    }
+
+    #[test]
+    fn it_increments_int_values_only() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrations::apply_all(&conn).unwrap();
+        let set = |name, value_type, value| {
+            let value = TypedValue::new(value_type, &value).unwrap();
+            single_value::set(&conn, "", name, &value, None, 1000).unwrap();
+        };
+        let add = |name: &str, by| add(&conn, "", name.to_string(), by, 2000).unwrap().map(|value| value.value.as_i64());
+
+        // A missing value counts from 0.
+        assert_eq!(add("count", 5), Ok(Some(5)));
+        assert_eq!(add("count", -7), Ok(Some(-2)));
+        assert_eq!(single_value::get(&conn, "", "count", 2000).unwrap().unwrap().revision, 2);
+
+        set("mode", ValueType::String, json!("safe"));
+        assert_eq!(add("mode", 1), Err(Refusal::NotAnInt));
+        set("max", ValueType::Int, json!(i64::MAX));
+        assert_eq!(add("max", 1), Err(Refusal::Overflow));
+        assert_eq!(add("max", -1), Ok(Some(i64::MAX - 1)));
+
+        let counted = super::add(&conn, "sc1", "count".to_string(), 1, 2000).unwrap().unwrap();
+        assert_eq!((counted.namespace.as_str(), counted.revision), ("sc1", 1));
+    }
+
+    #[test]
+    fn it_records_and_announces_deletes() {
+        let test = TestConfig::new();
+        let config = web::Data::new(test.config.clone());
+        let mode = TypedValue::new(ValueType::String, &json!("safe")).unwrap();
+        let conn = database::connection::open(test.config.db.as_path()).unwrap();
+        single_value::set(&conn, "sc1", "mode", &mode, None, TestConfig::START).unwrap();
+
+        let generation = test.config.value_changes.generation();
+        let remove = |name: &str| remove(config.clone(), "sc1".to_string(), name.to_string()).now_or_never().unwrap().status();
+        test.clock.advance(1000);
+        assert_eq!(remove("mode"), StatusCode::NO_CONTENT);
+        assert_eq!(test.config.value_changes.generation(), generation + 1);
+        assert_eq!(remove("mode"), StatusCode::NOT_FOUND);
+        assert_eq!(test.config.value_changes.generation(), generation + 1);
+
+        let changes = single_value::changes(&conn, "sc1", "", 0, 10).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[1].write.value.to_json(), Value::Null);
+        assert_eq!((changes[1].write.at, changes[1].write.revision), (Timestamp(TestConfig::START + 1000), 2));
+    }
+
+    #[test]
+    fn it_long_polls_off_the_executor() {
+        let test = TestConfig::new();
+        let mode = TypedValue::new(ValueType::String, &json!("safe")).unwrap();
+        let conn = database::connection::open(test.config.db.as_path()).unwrap();
+        single_value::set(&conn, "sc1", "mode", &mode, None, TestConfig::START).unwrap();
+
+        let poll = |since| {
+            let conn = database::connection::open(test.config.db.as_path()).unwrap();
+            let notifier = test.config.value_changes.clone();
+            actix_web::rt::System::new("test")
+                .block_on(async move { long_poll(conn, &notifier, "sc1", "", since, Duration::from_millis(10)).await })
+                .unwrap()
+        };
+        let watched = poll(0);
+        assert_eq!((watched.changes.len(), watched.next), (1, 1));
+        let watched = poll(1);
+        assert_eq!((watched.changes.len(), watched.next), (0, 1));
+    }
+
+    #[test]
+    fn it_reserves_the_watch_name() {
+        assert_eq!(check_key("sc1", WATCH).unwrap().status(), StatusCode::BAD_REQUEST);
+        assert!(check_key("sc1", "watchdog").is_none());
+    }
+}
@@ -1 +1 @@
-curl -F "metadata=@metadata.json" -F "packet=@packet.bin"  http://localhost:8000/packet
\ No newline at end of file
+curl -F "metadata=@metadata.json" -F "packet=@packet.bin"  http://localhost:8000/packets
\ No newline at end of file